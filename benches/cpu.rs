@@ -0,0 +1,91 @@
+use chip8::processor::{Cpu, CpuOptions};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+// instructions run per iteration
+const CYCLES: u64 = 1000;
+
+// sprites from the font drawn over and over, wrapping around the screen
+const DRAW: [u16; 8] = [
+    0xa000, // LD I, 0x000
+    0x6000, // LD V0, 0x00
+    0x6100, // LD V1, 0x00
+    0xd015, // DRW V0, V1, 5
+    0x7003, // ADD V0, 0x03
+    0x7101, // ADD V1, 0x01
+    0xd01f, // DRW V0, V1, 15
+    0x1206, // JP 0x206
+];
+
+// arithmetic and logic on registers, with a skip
+const ALU: [u16; 12] = [
+    0x6001, // LD V0, 0x01
+    0x6103, // LD V1, 0x03
+    0x8014, // ADD V0, V1
+    0x8105, // SUB V1, V0
+    0x8202, // AND V2, V0
+    0x8311, // OR V3, V1
+    0x8406, // SHR V4, V0
+    0x750d, // ADD V5, 0x0d
+    0x8573, // XOR V5, V7
+    0x3500, // SE V5, 0x00
+    0x1204, // JP 0x204
+    0x1204, // JP 0x204
+];
+
+// nested subroutine calls
+const CALL: [u16; 7] = [
+    0x2206, // CALL 0x206
+    0x7001, // ADD V0, 0x01
+    0x1200, // JP 0x200
+    0x220a, // CALL 0x20a
+    0x00ee, // RET
+    0x7101, // ADD V1, 0x01
+    0x00ee, // RET
+];
+
+fn cpu(program: &[u16]) -> Cpu {
+    let program: Vec<u8> = program.iter().flat_map(|i| i.to_be_bytes()).collect();
+    let options = CpuOptions {
+        seed: Some(0),
+        ..CpuOptions::default()
+    };
+    Cpu::with_options(&program, &options).unwrap()
+}
+
+fn bench_cycle(c: &mut Criterion) {
+    let mut group = c.benchmark_group("cycle");
+    group.throughput(Throughput::Elements(CYCLES));
+
+    for (name, program) in [("draw", &DRAW[..]), ("alu", &ALU[..]), ("call", &CALL[..])] {
+        let mut cpu = cpu(program);
+        let keys = [false; 16];
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for _ in 0..CYCLES {
+                    black_box(cpu.cycle(&keys).unwrap());
+                }
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// whole frames take the faster path through the cpu that headless runs use
+fn bench_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    group.throughput(Throughput::Elements(CYCLES));
+
+    for (name, program) in [("draw", &DRAW[..]), ("alu", &ALU[..]), ("call", &CALL[..])] {
+        let mut cpu = cpu(program);
+        let keys = [false; 16];
+        group.bench_function(name, |b| {
+            b.iter(|| black_box(cpu.run_frame(&keys, CYCLES as u32).unwrap()))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_cycle, bench_frame);
+criterion_main!(benches);
@@ -0,0 +1,65 @@
+#![no_main]
+
+use arbitrary::Arbitrary;
+use chip8::processor::{Cpu, CpuOptions, Quirks};
+use libfuzzer_sys::fuzz_target;
+
+// a program, the state to start it in and the keys held while it runs
+#[derive(Arbitrary, Debug)]
+struct Input {
+    program: Vec<u8>,
+    quirks: u8,
+    seed: u64,
+    keys: u16,
+    register: [u8; 16],
+    register_i: u16,
+    program_counter: u16,
+    stack_pointer: u8,
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+fuzz_target!(|input: Input| {
+    let profile = Quirks::PROFILES[input.quirks as usize % Quirks::PROFILES.len()];
+    let options = CpuOptions {
+        quirks: profile.parse().unwrap(),
+        seed: Some(input.seed),
+        ..CpuOptions::default()
+    };
+    // programs too big to load are rejected with an error, which is fine
+    let mut cpu = match Cpu::with_options(&input.program, &options) {
+        Ok(cpu) => cpu,
+        Err(_) => return,
+    };
+    let mut replay = cpu.clone();
+
+    for cpu in [&mut cpu, &mut replay] {
+        cpu.register = input.register;
+        cpu.register_i = input.register_i;
+        cpu.program_counter = input.program_counter as usize % 4096;
+        cpu.stack_pointer = input.stack_pointer as usize % 16;
+        cpu.delay_timer = input.delay_timer;
+        cpu.sound_timer = input.sound_timer;
+    }
+
+    let mut keys = [false; 16];
+    for (i, key) in keys.iter_mut().enumerate() {
+        *key = input.keys & (1 << i) != 0;
+    }
+
+    for _ in 0..100 {
+        let result = cpu.run_frame(&keys, 10);
+        assert!(cpu.program_counter < 4096);
+        assert!(cpu.stack_pointer < 16);
+
+        // the same seed and input give the same machine
+        assert_eq!(result.is_ok(), replay.run_frame(&keys, 10).is_ok());
+        assert_eq!(cpu.memory()[..], replay.memory()[..]);
+        assert_eq!(cpu.register, replay.register);
+        assert_eq!(cpu.display, replay.display);
+
+        if result.is_err() {
+            break;
+        }
+    }
+});
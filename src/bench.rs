@@ -0,0 +1,57 @@
+use crate::config::Config;
+use crate::input_movie::InputMovie;
+use crate::processor::Cpu;
+use crate::FRAME_RATE;
+use std::error;
+use std::time::{Duration, Instant};
+
+// a run is repeated until this much time has passed, so short programs are
+// still measured over enough instructions
+const MIN_DURATION: Duration = Duration::from_secs(2);
+
+// run a program headless as fast as it goes and report its speed
+pub fn run(config: &Config, program: &[u8]) -> Result<(), Box<dyn error::Error>> {
+    let input_movie = match &config.input_movie {
+        Some(filename) => Some(InputMovie::from_file(filename)?),
+        None => None,
+    };
+
+    let mut runs = 0;
+    let mut instructions = 0;
+    let start = Instant::now();
+
+    while runs == 0 || start.elapsed() < MIN_DURATION {
+        let mut cpu = Cpu::with_options(program, &config.cpu)?;
+
+        for frame in 0..config.frames {
+            let key_state = match &input_movie {
+                Some(movie) => movie.key_state(frame),
+                None => [false; 16],
+            };
+            cpu.run_frame(&key_state, config.instructions_per_frame)
+                .map_err(|e| format!("frame {}: {}", frame, e))?;
+        }
+
+        // cycles only counts instructions, not the time spent waiting for a key
+        instructions += cpu.cycles;
+        runs += 1;
+    }
+
+    let seconds = start.elapsed().as_secs_f64();
+    let frames = (runs * config.frames) as f64 / seconds;
+    println!(
+        "{} frames at {} ipf, run {} times in {:.2}s",
+        config.frames, config.instructions_per_frame, runs, seconds
+    );
+    println!(
+        "{:>12.0} instructions per second",
+        instructions as f64 / seconds
+    );
+    println!(
+        "{:>12.0} frames per second, {:.0}x full speed",
+        frames,
+        frames / FRAME_RATE as f64
+    );
+
+    Ok(())
+}
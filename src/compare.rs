@@ -0,0 +1,184 @@
+use crate::config::Config;
+use crate::input_movie::InputMovie;
+use crate::processor::{compare_lines, trace_header, Cpu, Difference, Tracer};
+use crate::FRAME_RATE;
+use std::collections::VecDeque;
+use std::error;
+use std::fs;
+
+// frames in a row without a traced instruction before giving up, the program
+// is probably waiting for a key the input movie never presses
+const MAX_IDLE_FRAMES: u32 = 60 * FRAME_RATE;
+
+// run a program without a window, checking each traced instruction against a
+// reference trace. true if the whole reference matched
+pub fn run(
+    config: &Config,
+    program: &[u8],
+    reference: &str,
+) -> Result<bool, Box<dyn error::Error>> {
+    let text = fs::read_to_string(reference)
+        .map_err(|e| format!("could not read '{}': {}", reference, e))?;
+
+    // the memory column is only compared if the reference has one
+    let mut options = config.trace_options.clone();
+    options.memory = text
+        .lines()
+        .find(|line| line.starts_with('#'))
+        .map_or(options.memory, |header| header.contains("memory"));
+
+    let expected: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let input_movie = match &config.input_movie {
+        Some(filename) => Some(InputMovie::from_file(filename)?),
+        None => None,
+    };
+
+    let mut cpu = Cpu::with_options(program, &config.cpu)?;
+    cpu.tracer = Some(Tracer::new(&options));
+
+    let mut previous: VecDeque<String> = VecDeque::with_capacity(config.compare_context + 1);
+    let mut index = 0;
+    let mut frame = 0;
+    let mut idle_frames = 0;
+
+    while index < expected.len() {
+        let key_state = match &input_movie {
+            Some(movie) => movie.key_state(frame),
+            None => [false; 16],
+        };
+        // a fault is checked once the lines traced before it have been compared
+        let fault = cpu
+            .run_frame(&key_state, config.instructions_per_frame)
+            .err();
+        frame += 1;
+
+        let lines = match &mut cpu.tracer {
+            Some(tracer) => tracer.take_lines(),
+            None => Vec::new(),
+        };
+
+        if lines.is_empty() && fault.is_none() {
+            idle_frames += 1;
+            if idle_frames == MAX_IDLE_FRAMES {
+                println!(
+                    "'{}' stopped matching after {} instructions: nothing was traced for {} frames, the reference continues with",
+                    reference, index, MAX_IDLE_FRAMES
+                );
+                println!("  {}", expected[index]);
+                return Ok(false);
+            }
+            continue;
+        }
+        idle_frames = 0;
+
+        for line in lines.into_iter().take(expected.len() - index) {
+            let differences = compare_lines(expected[index], &line, options.memory);
+            if !differences.is_empty() {
+                let lines = (expected[index], line.as_str());
+                print_report(
+                    reference,
+                    index,
+                    &previous,
+                    lines,
+                    &differences,
+                    options.memory,
+                );
+                return Ok(false);
+            }
+
+            previous.push_back(line);
+            if previous.len() > config.compare_context {
+                previous.pop_front();
+            }
+            index += 1;
+        }
+
+        // the reference went on past the instruction that faulted here
+        if let Some(fault) = fault {
+            if index < expected.len() {
+                print_context(reference, index, &previous, options.memory);
+                println!("expected:");
+                println!("  {}", expected[index]);
+                println!("actual:");
+                println!("  the program stopped: {}", fault);
+                return Ok(false);
+            }
+        }
+    }
+
+    println!(
+        "all {} instructions in '{}' matched",
+        expected.len(),
+        reference
+    );
+    Ok(true)
+}
+
+// the instructions leading up to a difference, then both lines with the
+// columns that differ marked
+fn print_report(
+    reference: &str,
+    index: usize,
+    previous: &VecDeque<String>,
+    (expected, actual): (&str, &str),
+    differences: &[Difference],
+    memory: bool,
+) {
+    print_context(reference, index, previous, memory);
+    println!("expected:");
+    println!("  {}", expected);
+    println!("actual:");
+    println!("  {}", actual);
+    println!("  {}", markers(actual, differences, memory));
+    println!();
+    for difference in differences {
+        println!(
+            "  {:<8} expected {:<10} actual {}",
+            difference.column, difference.expected, difference.actual
+        );
+    }
+}
+
+// where the difference is and the instructions that matched before it
+fn print_context(reference: &str, index: usize, previous: &VecDeque<String>, memory: bool) {
+    println!(
+        "first difference from '{}' at traced instruction {}",
+        reference, index
+    );
+    println!();
+    println!("  {}", trace_header(memory));
+    for line in previous {
+        println!("  {}", line);
+    }
+    println!();
+}
+
+// ^ under each column of the actual line that differs from the expected one
+fn markers(actual: &str, differences: &[Difference], memory: bool) -> String {
+    let header = trace_header(memory);
+    let columns: Vec<&str> = header.split_whitespace().skip(1).collect();
+
+    let mut markers = String::new();
+    let mut column = 0;
+    let mut in_word = false;
+    for c in actual.chars() {
+        if c == ' ' {
+            in_word = false;
+            markers.push(' ');
+            continue;
+        }
+        if !in_word {
+            in_word = true;
+            column += 1;
+        }
+        let marked = columns
+            .get(column - 1)
+            .is_some_and(|name| differences.iter().any(|d| d.column == *name));
+        markers.push(if marked { '^' } else { ' ' });
+    }
+    markers.trim_end().to_string()
+}
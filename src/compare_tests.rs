@@ -0,0 +1,45 @@
+use crate::compare;
+use crate::config::Config;
+use crate::processor::{trace_header, Cpu, Tracer};
+use std::env;
+use std::fs;
+
+// calls itself until the stack overflows
+const RECURSIVE_PROGRAM: [u8; 4] = [0x60, 0x01, 0x22, 0x02];
+
+// the trace of the program up to and including the instruction that faults
+fn trace_until_fault() -> Vec<String> {
+    let mut cpu = Cpu::new(&RECURSIVE_PROGRAM.to_vec()).unwrap();
+    cpu.tracer = Some(Tracer::new(&Config::new("").trace_options));
+    assert!(cpu.run_frame(&[false; 16], 100).is_err());
+    cpu.tracer.as_mut().unwrap().take_lines()
+}
+
+fn compare_with(test: &str, lines: &[String]) -> bool {
+    let path = env::temp_dir().join(format!("chip8-{}-{}.txt", test, std::process::id()));
+    let text = format!("{}\n{}\n", trace_header(false), lines.join("\n"));
+    fs::write(&path, text).unwrap();
+
+    let config = Config::new("recursive.ch8");
+    let result = compare::run(&config, &RECURSIVE_PROGRAM, path.to_str().unwrap());
+    fs::remove_file(&path).unwrap();
+    result.unwrap()
+}
+
+#[test]
+fn test_compare_fault() {
+    // a reference that goes on past the fault differs from the program there,
+    // it is reported instead of stopping the comparison with an error
+    let mut lines = trace_until_fault();
+    let last = lines.last().unwrap().clone();
+    lines.push(last);
+    assert!(!compare_with("compare_fault", &lines));
+}
+
+#[test]
+fn test_compare_fault_at_end() {
+    // a reference that stops where the program faults matches all the way
+    let lines = trace_until_fault();
+    assert_eq!(lines.len(), 17);
+    assert!(compare_with("compare_fault_at_end", &lines));
+}
@@ -0,0 +1,468 @@
+use crate::io::AudioSettings;
+use crate::io::BeepSound;
+use crate::io::WINDOW_SCALE;
+use crate::keymap::Keymap;
+use crate::palette::Palette;
+use crate::processor::CpuOptions;
+use crate::processor::TraceOptions;
+use crate::settings::Settings;
+use crate::INSTRUCTIONS_PER_FRAME;
+use std::error;
+use std::path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub const USAGE: &str = "\
+Usage: chip8 [run] <program> [options]
+       chip8 [options]
+       chip8 bench <program> [options]
+       chip8 recompile <program> [--output <file>] [options]
+       chip8 help
+       chip8 --version
+
+Commands:
+  run                            run a program (the default)
+  bench                          run a program without a window as fast as
+                                 possible, --frames at a time, and report
+                                 instructions per second
+  recompile                      translate a program into the source of a rust
+                                 program that runs it with the chip8 library,
+                                 using the --quirks and --ipf given, written to
+                                 --output or printed
+  help                           show this message
+
+Without a program, a launcher is shown to pick one from. It lists .ch8, .sc8
+and .xo8 files and remembers recently played programs and favorites.
+
+Development:
+  --watch                        reload the program when the file changes
+  --watch-replay                 reload, then replay the input movie up to the
+                                 frame the program was on
+  --profile <file>               count where instructions are spent and write a
+                                 report on exit, as json if the file name ends
+                                 in .json and text otherwise
+  --coverage <file>              write an lcov tracefile on exit, over a
+                                 disassembly saved next to it as .asm
+  --heatmap <png file>           write an image of how each byte of memory was
+                                 executed, read and written on exit
+  --trace <file>                 log the state before every instruction, one
+                                 fixed width line each, for comparing with diff
+  --trace-range <start>-<end>    only trace instructions in an address range,
+                                 can be given more than once
+  --trace-start <cycle>          first instruction to trace, counting from 0
+  --trace-stop <cycle>           last instruction to trace
+  --trace-memory                 add a checksum of memory to each line
+  --compare <trace file>         run without a window and check every
+                                 instruction against a reference trace, stopping
+                                 at the first difference. filter options must
+                                 match the ones the reference was made with
+  --context <n>                  instructions shown before a difference
+                                 (default 10)
+  --symbols <file>               labels for reports, lines of <address> <label>
+                                 (default <program>.sym if it exists)
+
+Settings:
+  --config <file>                config file to use instead of the default
+                                 (~/.config/chip8/config.toml)
+  --database <dir>               rom database to use instead of the default
+                                 (~/.config/chip8/database)
+
+Emulation:
+  --ipf <n>                      instructions per 60hz frame (default 10)
+  --quirks <profile>             default, chip8, schip or xochip
+  --start-address <address>      where the program is loaded (default 0x200)
+  --seed <n>                     seed the random number generator
+  --debug                        print every executed instruction to stderr
+
+Display:
+  --scale <n>                    window pixels per chip8 pixel (default 20)
+  --palette <palette>            default, green, amber, gray, white or
+                                 two hex colors: <background>,<pixel>
+
+Audio:
+  --mute                         start muted
+  --beep-sample[=<wav file>]     play a sample instead of a tone
+  --waveform <waveform>          square, sine, triangle or noise
+  --frequency <hz>               tone frequency (default 240)
+  --volume <0-1>                 buzzer volume (default 0.25)
+  --attack <ms>                  fade in length (default 5)
+  --release <ms>                 fade out length (default 5)
+
+Recording:
+  --record-audio <wav file>      record the buzzer output
+  --record-video <file>          record the display to a .gif or .png (apng)
+  --screenshot-native            save screenshots at 64x32 instead of window size
+  --screenshot-clipboard         also copy screenshots to the clipboard
+
+Headless:
+  --headless                     run without a window or audio device
+  --frames <n>                   frames to run headless (default 600)
+  --input-movie <file>           keypad input for headless runs and --watch-replay
+
+  -h, --help                     show this message
+  -V, --version                  show the version
+
+Keys:
+  Escape quit, F2 reset, F5 reload the program from disk
+  P pause, N advance one frame while paused, . step one instruction while
+  paused, = and - change the speed, hold Tab to fast forward
+  M mute, F3 save settings for this program, F9 record audio, F10 record
+  video, F12 screenshot
+  F1 pause menu, F6 save state, F7 load state, F8 show the frame rate
+
+Launcher keys:
+  Up and Down select, Page Up and Page Down skip, Enter play, Backspace parent
+  directory, Tab next list, F add or remove a favorite, Escape quit. Escape
+  while playing returns to the launcher. Game controllers use the d-pad, A to
+  play, B to go back, X for favorites and the shoulder buttons to switch lists";
+
+pub enum Command {
+    Run(Box<Config>),
+    Launch(Box<Config>), // no program was given
+    Bench(Box<Config>),
+    Recompile(Box<Config>),
+    Help,
+    Version,
+}
+
+// settings for running a program, from the command line
+#[derive(Clone)]
+pub struct Config {
+    pub filename: String,
+    pub instructions_per_frame: u32,
+    pub cpu: CpuOptions,
+    pub scale: u32,
+    pub palette: Palette,
+    pub keymap: Keymap,
+    pub audio: AudioSettings,
+    pub mute: bool,
+    pub config_file: Option<PathBuf>, // overrides the default config file location
+    pub overrides: Settings,          // settings given on the command line
+    pub database: Option<PathBuf>,    // overrides the default database location
+    pub title: Option<String>,        // from the database
+    pub key_hints: Vec<(String, usize)>, // extra key bindings from the database
+    pub record_audio: Option<String>,
+    pub record_video: Option<String>,
+    pub screenshot_native: bool, // one image pixel per chip8 pixel instead of window scale
+    pub screenshot_clipboard: bool,
+    pub headless: bool,
+    pub frames: u32,                 // frames to run in headless mode
+    pub input_movie: Option<String>, // scripted input for headless runs
+    pub watch: bool,                 // reload the program when the file changes
+    pub watch_replay: bool,          // replay the input movie after reloading
+    pub profile: Option<String>,     // where the profiler report is written
+    pub symbols: Option<String>,     // labels for reports, instead of <rom>.sym
+    pub coverage: Option<String>,    // lcov tracefile, with a disassembly next to it
+    pub heatmap: Option<String>,     // png of memory accesses
+    pub trace: Option<String>,       // file each executed instruction is logged to
+    pub trace_options: TraceOptions,
+    pub compare: Option<String>, // reference trace to check the program against
+    pub compare_context: usize,  // matching instructions shown before a difference
+    pub output: Option<String>,  // where recompile writes the source
+}
+
+impl Config {
+    pub fn new(filename: &str) -> Self {
+        Config {
+            filename: filename.to_string(),
+            instructions_per_frame: INSTRUCTIONS_PER_FRAME,
+            cpu: CpuOptions::default(),
+            scale: WINDOW_SCALE,
+            palette: Palette::default(),
+            keymap: Keymap::default(),
+            audio: AudioSettings::default(),
+            mute: false,
+            config_file: None,
+            overrides: Settings::default(),
+            database: None,
+            title: None,
+            key_hints: Vec::new(),
+            record_audio: None,
+            record_video: None,
+            screenshot_native: false,
+            screenshot_clipboard: false,
+            headless: false,
+            frames: 600,
+            input_movie: None,
+            watch: false,
+            watch_replay: false,
+            profile: None,
+            symbols: None,
+            coverage: None,
+            heatmap: None,
+            trace: None,
+            trace_options: TraceOptions::default(),
+            compare: None,
+            compare_context: 10,
+            output: None,
+        }
+    }
+
+    pub fn apply(&mut self, settings: &Settings) {
+        if let Some(ipf) = settings.ipf {
+            self.instructions_per_frame = ipf;
+        }
+        if let Some(start_address) = settings.start_address {
+            self.cpu.start_address = start_address;
+        }
+        if let Some(quirks) = settings.quirks {
+            self.cpu.quirks = quirks;
+        }
+        if let Some(palette) = settings.palette {
+            self.palette = palette;
+        }
+        if let Some(keymap) = &settings.keymap {
+            self.keymap = keymap.clone();
+        }
+        if let Some(mute) = settings.mute {
+            self.mute = mute;
+        }
+        if let Some(waveform) = settings.waveform {
+            self.audio.waveform = waveform;
+        }
+        if let Some(frequency) = settings.frequency {
+            self.audio.frequency = frequency;
+        }
+        if let Some(volume) = settings.volume {
+            self.audio.volume = volume;
+        }
+        if let Some(attack) = settings.attack {
+            self.audio.attack_ms = attack;
+        }
+        if let Some(release) = settings.release {
+            self.audio.release_ms = release;
+        }
+    }
+
+    // the current value of everything the config file can hold
+    pub fn settings(&self) -> Settings {
+        Settings {
+            name: None,
+            ipf: Some(self.instructions_per_frame),
+            start_address: Some(self.cpu.start_address),
+            quirks: Some(self.cpu.quirks),
+            palette: Some(self.palette),
+            keymap: Some(self.keymap.clone()),
+            mute: Some(self.mute),
+            waveform: Some(self.audio.waveform),
+            frequency: Some(self.audio.frequency),
+            volume: Some(self.audio.volume),
+            attack: Some(self.audio.attack_ms),
+            release: Some(self.audio.release_ms),
+        }
+    }
+}
+
+// args excludes the name the program was called with
+pub fn parse_args(args: &[String]) -> Result<Command, Box<dyn error::Error>> {
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        return Ok(Command::Help);
+    }
+
+    if args.iter().any(|a| a == "-V" || a == "--version") {
+        return Ok(Command::Version);
+    }
+
+    let config = match args.first().map(|a| a.as_str()) {
+        Some("help") => return Ok(Command::Help),
+        Some("run") => parse_run_args(&args[1..])?,
+        Some("bench") => {
+            let config = parse_run_args(&args[1..])?;
+            if config.filename.is_empty() {
+                return Err("no program given".into());
+            }
+            return Ok(Command::Bench(Box::new(config)));
+        }
+        Some("recompile") => {
+            let config = parse_run_args(&args[1..])?;
+            if config.filename.is_empty() {
+                return Err("no program given".into());
+            }
+            return Ok(Command::Recompile(Box::new(config)));
+        }
+        _ => parse_run_args(args)?,
+    };
+
+    if config.filename.is_empty() {
+        Ok(Command::Launch(Box::new(config)))
+    } else {
+        Ok(Command::Run(Box::new(config)))
+    }
+}
+
+fn parse_run_args(args: &[String]) -> Result<Config, Box<dyn error::Error>> {
+    let mut filename = None;
+    let mut config = Config::new("");
+    let mut cli = Settings::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') {
+            if filename.is_some() {
+                return Err(format!("unexpected argument '{}'", arg).into());
+            }
+            filename = Some(arg.clone());
+            continue;
+        }
+
+        // values can be given as --option=value or --option value
+        let (option, inline_value) = match arg.find('=') {
+            Some(i) => (&arg[..i], Some(&arg[i + 1..])),
+            None => (&arg[..], None),
+        };
+        let mut value = || -> Result<&str, String> {
+            match inline_value {
+                Some(v) => Ok(v),
+                None => args
+                    .next()
+                    .map(|v| v.as_str())
+                    .ok_or(format!("option '{}' needs a value", option)),
+            }
+        };
+
+        match option {
+            "--ipf" => cli.ipf = Some(parse_positive(option, value()?)?),
+            "--quirks" => cli.quirks = Some(value()?.parse()?),
+            "--start-address" => cli.start_address = Some(parse_address(option, value()?)?),
+            "--seed" => config.cpu.seed = Some(parse_value(option, value()?)?),
+            "--debug" => config.cpu.debug = true,
+            "--config" => config.config_file = Some(PathBuf::from(value()?)),
+            "--database" => {
+                let database = value()?;
+                check_exists(database)?;
+                config.database = Some(PathBuf::from(database));
+            }
+            "--scale" => config.scale = parse_positive(option, value()?)?,
+            "--palette" => cli.palette = Some(value()?.parse()?),
+            "--mute" => cli.mute = Some(true),
+            "--beep-sample" => match inline_value {
+                Some(sample) => {
+                    check_exists(sample)?;
+                    config.audio.beep = BeepSound::Sample(sample.to_string());
+                }
+                None => config.audio.beep = BeepSound::BundledSample,
+            },
+            "--waveform" => cli.waveform = Some(value()?.parse()?),
+            "--frequency" => cli.frequency = Some(parse_value(option, value()?)?),
+            "--volume" => cli.volume = Some(parse_value(option, value()?)?),
+            "--attack" => cli.attack = Some(parse_value(option, value()?)?),
+            "--release" => cli.release = Some(parse_value(option, value()?)?),
+            "--record-audio" => config.record_audio = Some(value()?.to_string()),
+            "--record-video" => config.record_video = Some(value()?.to_string()),
+            "--screenshot-native" => config.screenshot_native = true,
+            "--screenshot-clipboard" => config.screenshot_clipboard = true,
+            "--watch" => config.watch = true,
+            "--watch-replay" => {
+                config.watch = true;
+                config.watch_replay = true;
+            }
+            "--profile" => {
+                config.profile = Some(value()?.to_string());
+                config.cpu.profile = true;
+            }
+            "--coverage" => {
+                config.coverage = Some(value()?.to_string());
+                config.cpu.coverage = true;
+            }
+            "--heatmap" => {
+                config.heatmap = Some(value()?.to_string());
+                config.cpu.coverage = true;
+            }
+            "--trace" => config.trace = Some(value()?.to_string()),
+            "--trace-range" => {
+                let range = value()?;
+                let (start, end) = range
+                    .split_once('-')
+                    .ok_or(format!("expected <start>-<end> for '{}'", option))?;
+                let (start, end) = (parse_address(option, start)?, parse_address(option, end)?);
+                if start > end {
+                    return Err(format!("empty range '{}' for '{}'", range, option).into());
+                }
+                config.trace_options.ranges.push((start, end));
+            }
+            "--trace-start" => config.trace_options.start = parse_value(option, value()?)?,
+            "--trace-stop" => config.trace_options.stop = Some(parse_value(option, value()?)?),
+            "--trace-memory" => config.trace_options.memory = true,
+            "--compare" => {
+                let reference = value()?;
+                check_exists(reference)?;
+                config.compare = Some(reference.to_string());
+            }
+            "--context" => config.compare_context = parse_value(option, value()?)?,
+            "--output" => config.output = Some(value()?.to_string()),
+            "--symbols" => {
+                let symbols = value()?;
+                check_exists(symbols)?;
+                config.symbols = Some(symbols.to_string());
+            }
+            "--headless" => config.headless = true,
+            "--frames" => config.frames = parse_value(option, value()?)?,
+            "--input-movie" => {
+                let movie = value()?;
+                check_exists(movie)?;
+                config.input_movie = Some(movie.to_string());
+            }
+            _ => return Err(format!("unknown option '{}'", option).into()),
+        }
+    }
+
+    if config.watch_replay && config.input_movie.is_none() {
+        return Err("'--watch-replay' needs an '--input-movie' to replay".into());
+    }
+
+    // a replay only matches the run it replaces if cxnn gives the same numbers,
+    // so every program started in the session gets the same seed
+    if config.watch_replay && config.cpu.seed.is_none() {
+        config.cpu.seed = Some(rand::random());
+    }
+
+    cli.check("--")?;
+    config.overrides = cli;
+
+    // without a program the launcher picks one, which needs a window
+    match filename {
+        Some(filename) => {
+            check_exists(&filename)?;
+            config.filename = filename;
+        }
+        None if config.headless || config.watch || config.compare.is_some() => {
+            return Err("no program given".into())
+        }
+        None => {}
+    }
+
+    Ok(config)
+}
+
+fn check_exists(filename: &str) -> Result<(), Box<dyn error::Error>> {
+    if !path::Path::new(filename).exists() {
+        return Err(format!("file '{}' does not exist", filename).into());
+    }
+    Ok(())
+}
+
+fn parse_value<T: FromStr>(option: &str, value: &str) -> Result<T, Box<dyn error::Error>> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("invalid value '{}' for '{}'", value, option).into())
+}
+
+fn parse_positive(option: &str, value: &str) -> Result<u32, Box<dyn error::Error>> {
+    match parse_value::<u32>(option, value)? {
+        0 => Err(format!("'{}' must be at least 1", option).into()),
+        x => Ok(x),
+    }
+}
+
+// decimal, or hex with a 0x prefix
+fn parse_address(option: &str, value: &str) -> Result<usize, Box<dyn error::Error>> {
+    let address = match value.strip_prefix("0x") {
+        Some(hex) => usize::from_str_radix(hex, 16).ok(),
+        None => value.parse::<usize>().ok(),
+    };
+
+    match address {
+        Some(a) if a < 4096 => Ok(a),
+        _ => Err(format!("invalid address '{}' for '{}'", value, option).into()),
+    }
+}
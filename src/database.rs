@@ -0,0 +1,183 @@
+use crate::processor::Quirks;
+use crate::settings::Settings;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// a local copy of the community chip8 database, a directory holding its
+// programs.json and platforms.json
+pub struct Database {
+    programs: Vec<Program>,
+    platforms: Vec<Platform>,
+}
+
+// what the database knows about a rom
+pub struct RomInfo {
+    pub title: String,
+    pub authors: Vec<String>,
+    pub platform: Option<String>,
+    pub settings: Settings,
+    pub key_hints: Vec<(String, usize)>, // extra keyboard keys for the rom's controls
+}
+
+#[derive(Deserialize)]
+struct Program {
+    title: String,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    roms: HashMap<String, Rom>, // keyed by sha-1
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Rom {
+    #[serde(default)]
+    platforms: Vec<String>,
+    #[serde(default)]
+    quirky_platforms: HashMap<String, PlatformQuirks>,
+    tickrate: Option<u32>,
+    start_address: Option<usize>,
+    #[serde(default)]
+    keys: HashMap<String, u8>,
+    colors: Option<Colors>,
+}
+
+#[derive(Deserialize)]
+struct Colors {
+    #[serde(default)]
+    pixels: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Platform {
+    id: String,
+    default_tickrate: Option<u32>,
+    #[serde(default)]
+    quirks: PlatformQuirks,
+}
+
+// quirks as the database names them, unset ones are taken from the platform
+#[derive(Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlatformQuirks {
+    shift: Option<bool>, // 8xy6/8xye shift Vx in place
+    memory_increment_by_x: Option<bool>,
+    memory_leave_i_unchanged: Option<bool>,
+    wrap: Option<bool>, // sprites wrap around the screen edges
+    jump: Option<bool>,
+    logic: Option<bool>,
+}
+
+impl Database {
+    // $XDG_CONFIG_HOME/chip8/database on linux
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chip8").join("database"))
+    }
+
+    pub fn load(dir: &Path) -> Result<Self, Box<dyn error::Error>> {
+        Ok(Database {
+            programs: read_json(&dir.join("programs.json"))?,
+            platforms: read_json(&dir.join("platforms.json"))?,
+        })
+    }
+
+    pub fn lookup(&self, hash: &str) -> Option<RomInfo> {
+        let (program, rom) = self
+            .programs
+            .iter()
+            .find_map(|p| p.roms.get(hash).map(|rom| (p, rom)))?;
+
+        let platform_id = rom.platforms.first();
+        let platform = platform_id.and_then(|id| self.platforms.iter().find(|p| &p.id == id));
+
+        let mut settings = Settings {
+            ipf: rom
+                .tickrate
+                .or_else(|| platform.and_then(|p| p.default_tickrate)),
+            start_address: rom.start_address,
+            ..Settings::default()
+        };
+
+        if let Some(platform) = platform {
+            let quirks = rom
+                .quirky_platforms
+                .get(&platform.id)
+                .map_or(platform.quirks, |q| q.or(&platform.quirks));
+            settings.quirks = Some(quirks.to_quirks());
+        }
+
+        if let Some(colors) = &rom.colors {
+            if let [background, pixel, ..] = colors.pixels.as_slice() {
+                settings.palette = format!("{},{}", background, pixel).parse().ok();
+            }
+        }
+
+        Some(RomInfo {
+            title: program.title.clone(),
+            authors: program.authors.clone(),
+            platform: platform_id.cloned(),
+            settings,
+            key_hints: key_hints(&rom.keys),
+        })
+    }
+}
+
+impl PlatformQuirks {
+    fn or(&self, other: &PlatformQuirks) -> PlatformQuirks {
+        PlatformQuirks {
+            shift: self.shift.or(other.shift),
+            memory_increment_by_x: self.memory_increment_by_x.or(other.memory_increment_by_x),
+            memory_leave_i_unchanged: self
+                .memory_leave_i_unchanged
+                .or(other.memory_leave_i_unchanged),
+            wrap: self.wrap.or(other.wrap),
+            jump: self.jump.or(other.jump),
+            logic: self.logic.or(other.logic),
+        }
+    }
+
+    // vblank waits are not emulated
+    fn to_quirks(self) -> Quirks {
+        let increment_by_x = self.memory_increment_by_x.unwrap_or(false);
+        Quirks {
+            shift_uses_vy: !self.shift.unwrap_or(false),
+            load_store_moves_i: !self.memory_leave_i_unchanged.unwrap_or(false) || increment_by_x,
+            load_store_moves_i_by_x: increment_by_x,
+            jump_uses_vx: self.jump.unwrap_or(false),
+            logic_resets_vf: self.logic.unwrap_or(false),
+            clip_sprites: !self.wrap.unwrap_or(false),
+        }
+    }
+}
+
+// arrow keys, space and shift for the controls the database lists
+fn key_hints(keys: &HashMap<String, u8>) -> Vec<(String, usize)> {
+    let bindings = [
+        ("up", "Up"),
+        ("down", "Down"),
+        ("left", "Left"),
+        ("right", "Right"),
+        ("a", "Space"),
+        ("b", "Left Shift"),
+    ];
+
+    bindings
+        .iter()
+        .filter_map(|(control, key)| {
+            keys.get(*control)
+                .filter(|&&k| k < 16)
+                .map(|&k| (key.to_string(), k as usize))
+        })
+        .collect()
+}
+
+fn read_json<T: serde::de::DeserializeOwned>(path: &Path) -> Result<T, Box<dyn error::Error>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("could not read '{}': {}", path.display(), e))?;
+    serde_json::from_str(&text)
+        .map_err(|e| format!("could not read '{}': {}", path.display(), e).into())
+}
@@ -0,0 +1,96 @@
+use crate::database::Database;
+use crate::processor::Quirks;
+use std::env;
+use std::fs;
+
+const PROGRAMS: &str = r##"[
+  {
+    "title": "Pong",
+    "authors": ["Paul Vervalin"],
+    "roms": {
+      "aaaa": {
+        "platforms": ["originalChip8"],
+        "keys": { "up": 1, "down": 4, "a": 16 },
+        "colors": { "pixels": ["#000000", "#ffffff"] }
+      }
+    }
+  },
+  {
+    "title": "Octo Game",
+    "roms": {
+      "bbbb": {
+        "platforms": ["xochip"],
+        "tickrate": 100,
+        "quirkyPlatforms": { "xochip": { "memoryIncrementByX": true } }
+      }
+    }
+  }
+]"##;
+
+const PLATFORMS: &str = r#"[
+  {
+    "id": "originalChip8",
+    "defaultTickrate": 15,
+    "quirks": {
+      "shift": false, "memoryIncrementByX": false, "memoryLeaveIUnchanged": false,
+      "wrap": false, "jump": false, "vblank": true, "logic": true
+    }
+  },
+  {
+    "id": "xochip",
+    "defaultTickrate": 1000,
+    "quirks": {
+      "shift": false, "memoryIncrementByX": false, "memoryLeaveIUnchanged": false,
+      "wrap": true, "jump": false, "vblank": false, "logic": false
+    }
+  }
+]"#;
+
+fn load(test: &str) -> Database {
+    let dir = env::temp_dir().join(format!("chip8-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("programs.json"), PROGRAMS).unwrap();
+    fs::write(dir.join("platforms.json"), PLATFORMS).unwrap();
+    let database = Database::load(&dir).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    database
+}
+
+#[test]
+fn test_lookup() {
+    let database = load("database_lookup");
+    assert!(database.lookup("cccc").is_none());
+
+    let info = database.lookup("aaaa").unwrap();
+    assert_eq!(info.title, "Pong");
+    assert_eq!(info.authors, ["Paul Vervalin"]);
+    assert_eq!(info.platform.as_deref(), Some("originalChip8"));
+    assert_eq!(info.settings.ipf, Some(15));
+    assert!(info.settings.palette.is_some());
+    // key 16 is not a chip8 key
+    assert_eq!(
+        info.key_hints,
+        [("Up".to_string(), 1), ("Down".to_string(), 4)]
+    );
+}
+
+#[test]
+fn test_platform_quirks() {
+    let database = load("database_platform_quirks");
+    let quirks = database.lookup("aaaa").unwrap().settings.quirks;
+    assert_eq!(quirks, Some(Quirks::chip8()));
+}
+
+#[test]
+fn test_rom_quirks() {
+    let database = load("database_rom_quirks");
+    let info = database.lookup("bbbb").unwrap();
+    assert_eq!(info.settings.ipf, Some(100));
+
+    // the rom's own quirks are put over the platform's
+    let quirks = info.settings.quirks.unwrap();
+    assert!(quirks.load_store_moves_i);
+    assert!(quirks.load_store_moves_i_by_x);
+    assert!(!quirks.clip_sprites);
+    assert!(quirks.shift_uses_vy);
+}
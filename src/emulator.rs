@@ -0,0 +1,571 @@
+use crate::config::Config;
+use crate::file_watcher::FileWatcher;
+use crate::input_movie::InputMovie;
+use crate::io::AudioDriver;
+use crate::io::DisplayDriver;
+use crate::io::EmulatorEvent;
+use crate::io::InputDriver;
+use crate::overlay::{MenuAction, Overlay};
+use crate::processor::Cpu;
+use crate::processor::CpuOptions;
+use crate::processor::TraceFile;
+use crate::recording;
+use crate::recording::AudioRecorder;
+use crate::recording::Screenshotter;
+use crate::recording::VideoRecorder;
+use crate::settings;
+use crate::{load_rom, read_program, save_before_fault, save_reports};
+use crate::{FRAME_RATE, MAX_INSTRUCTIONS_PER_FRAME, VIDEO_SCALE};
+use spin_sleep::LoopHelper;
+use std::error;
+use std::iter;
+use std::time::{Duration, Instant};
+
+// the window, audio device and input, shared between the launcher and the
+// programs it starts
+pub struct Drivers {
+    _sdl_context: sdl2::Sdl,
+    pub display: DisplayDriver,
+    pub audio: AudioDriver,
+    pub input: InputDriver,
+}
+
+// why the emulator stopped
+#[derive(PartialEq)]
+pub enum Exit {
+    Quit,
+    Back, // return to the launcher
+}
+
+impl Drivers {
+    pub fn new(config: &Config) -> Result<Self, Box<dyn error::Error>> {
+        let sdl_context = sdl2::init()?;
+        let display = DisplayDriver::new(&sdl_context, &config.palette, config.scale)?;
+        let audio = AudioDriver::new(&sdl_context, &config.audio)?;
+        let input = InputDriver::new(&sdl_context, &config.keymap, &config.key_hints)?;
+
+        Ok(Drivers {
+            _sdl_context: sdl_context,
+            display,
+            audio,
+            input,
+        })
+    }
+}
+
+// run a program until the window is closed, or until escape is pressed when
+// started from the launcher. cli_config holds the settings from the command
+// line alone, the starting point for programs loaded while running
+pub fn run(
+    drivers: &mut Drivers,
+    cli_config: &Config,
+    mut config: Config,
+    mut program: Vec<u8>,
+    from_launcher: bool,
+) -> Result<Exit, Box<dyn error::Error>> {
+    let display_driver = &mut drivers.display;
+    let audio_driver = &mut drivers.audio;
+    let input_driver = &mut drivers.input;
+
+    let mut cpu = Cpu::with_options(&program, &config.cpu)?;
+    configure_drivers(display_driver, audio_driver, input_driver, &config)?;
+
+    let mut overlay = Overlay::new();
+
+    let mut trace = match &config.trace {
+        Some(filename) => Some(TraceFile::start(filename, &mut cpu, &config.trace_options)?),
+        None => None,
+    };
+
+    let mut audio_recorder = match &config.record_audio {
+        Some(filename) => Some(start_audio_recording(audio_driver, &mut overlay, filename)?),
+        None => None,
+    };
+
+    let mut video_recorder = match &config.record_video {
+        Some(filename) => Some(start_video_recording(&config, &mut overlay, filename)?),
+        None => None,
+    };
+
+    let mut screenshotter = new_screenshotter(&config)?;
+
+    let mut loop_helper = LoopHelper::builder().build_with_target_rate(FRAME_RATE);
+    let mut fast_loop_helper = LoopHelper::builder()
+        .report_interval_s(0.5)
+        .build_without_target_rate();
+
+    let mut watcher = if config.watch {
+        Some(FileWatcher::new(&config.filename))
+    } else {
+        None
+    };
+
+    // replayed up to the current frame after the program changes on disk
+    let replay_movie = match (&config.input_movie, config.watch_replay) {
+        (Some(filename), true) => Some(InputMovie::from_file(filename)?),
+        _ => None,
+    };
+
+    let mut ipf = config.instructions_per_frame;
+    let mut frames = FrameHistory::default(); // since the program was started
+    let mut paused = false;
+    let mut fast_forward_speed = 1.0;
+    let mut title = String::new();
+    let mut needs_render = false;
+    let mut last_render = Instant::now();
+    let mut saved_state: Option<(Cpu, FrameHistory)> = None;
+
+    loop {
+        let menu_open = overlay.menu_open();
+        let fast_forward = input_driver.fast_forward() && !paused && !menu_open;
+        if fast_forward {
+            fast_loop_helper.loop_start();
+        } else {
+            loop_helper.loop_start();
+        }
+
+        let (key_state, mut events) = if menu_open {
+            ([false; 16], Vec::new())
+        } else {
+            input_driver.poll_events()
+        };
+        let mut run_frame = !paused && !menu_open;
+
+        // the pause menu takes over input while it is open
+        if menu_open {
+            for menu_event in input_driver.poll_menu() {
+                match overlay.handle_menu(menu_event) {
+                    Some(MenuAction::Event(event)) => events.push(event),
+                    Some(MenuAction::Palette(palette)) => {
+                        config.palette = palette;
+                        display_driver.set_palette(&palette);
+                        screenshotter.set_palette(&palette);
+                    }
+                    Some(MenuAction::Quirks(quirks)) => {
+                        config.cpu.quirks = quirks;
+                        cpu.quirks = quirks;
+                    }
+                    Some(MenuAction::Keymap(keymap)) => {
+                        config.keymap = *keymap;
+                        input_driver.set_keymap(&config.keymap, &config.key_hints)?;
+                    }
+                    None => {}
+                }
+            }
+        }
+        let mut reload = watcher.as_mut().is_some_and(|w| w.changed());
+
+        for event in events {
+            match event {
+                EmulatorEvent::Quit | EmulatorEvent::Back => {
+                    if let Some(recorder) = audio_recorder.take() {
+                        stop_audio_recording(audio_driver, &mut overlay, recorder)?;
+                    }
+                    if let Some(recorder) = video_recorder.take() {
+                        stop_video_recording(&mut overlay, recorder)?;
+                    }
+                    audio_driver.stop_beep();
+                    if let Some(mut trace) = trace.take() {
+                        trace.write(&mut cpu)?;
+                        trace.finish()?;
+                    }
+                    save_reports(&config, &cpu, program.len())?;
+
+                    return match event {
+                        EmulatorEvent::Back if from_launcher => Ok(Exit::Back),
+                        _ => Ok(Exit::Quit),
+                    };
+                }
+                EmulatorEvent::Reset => {
+                    reset(&mut cpu, audio_driver, &program, &config.cpu)?;
+                    frames = FrameHistory::default();
+                    needs_render = true;
+                }
+                EmulatorEvent::Reload => reload = true,
+                EmulatorEvent::Open(filename) => {
+                    let loaded =
+                        load_rom(cli_config, &filename).and_then(|(new_config, new_program)| {
+                            let new_cpu = Cpu::with_options(&new_program, &new_config.cpu)?;
+                            let new_screenshotter = new_screenshotter(&new_config)?;
+                            configure_drivers(
+                                display_driver,
+                                audio_driver,
+                                input_driver,
+                                &new_config,
+                            )?;
+                            Ok((new_config, new_program, new_cpu, new_screenshotter))
+                        });
+
+                    match loaded {
+                        Ok((new_config, new_program, mut new_cpu, new_screenshotter)) => {
+                            // reports are for a single program, the trace
+                            // goes on into the next
+                            new_cpu.tracer = cpu.tracer.take();
+                            config = new_config;
+                            program = new_program;
+                            cpu = new_cpu;
+                            frames = FrameHistory::default();
+                            ipf = config.instructions_per_frame;
+                            if config.watch {
+                                watcher = Some(FileWatcher::new(&config.filename));
+                            }
+                            screenshotter = new_screenshotter;
+                            saved_state = None;
+                            needs_render = true;
+                            overlay.info(&format!("loaded '{}'", filename));
+                        }
+                        Err(e) => overlay.error(&format!("could not load '{}': {}", filename, e)),
+                    }
+                }
+                EmulatorEvent::Mute => {
+                    audio_driver.toggle_mute();
+                    overlay.info(if audio_driver.is_muted() {
+                        "muted"
+                    } else {
+                        "unmuted"
+                    });
+                }
+                EmulatorEvent::SaveSettings => {
+                    let mut current = config.settings();
+                    current.ipf = Some(ipf);
+                    current.mute = Some(audio_driver.is_muted());
+                    match settings::save_rom_settings(&config, &program, &current) {
+                        Ok(path) => {
+                            overlay.info(&format!("saved settings to '{}'", path.display()))
+                        }
+                        Err(e) => overlay.error(&e.to_string()),
+                    }
+                }
+                EmulatorEvent::RecordAudio => match audio_recorder.take() {
+                    Some(recorder) => stop_audio_recording(audio_driver, &mut overlay, recorder)?,
+                    None => {
+                        let filename = recording::timestamped_filename("chip8", "wav");
+                        audio_recorder = Some(start_audio_recording(
+                            audio_driver,
+                            &mut overlay,
+                            &filename,
+                        )?);
+                    }
+                },
+                EmulatorEvent::Screenshot => match screenshotter.take(&cpu.display) {
+                    Ok(filename) => overlay.info(&format!("saved screenshot '{}'", filename)),
+                    Err(e) => overlay.error(&e.to_string()),
+                },
+                EmulatorEvent::RecordVideo => match video_recorder.take() {
+                    Some(recorder) => stop_video_recording(&mut overlay, recorder)?,
+                    None => {
+                        let filename = recording::timestamped_filename("chip8", "gif");
+                        video_recorder =
+                            Some(start_video_recording(&config, &mut overlay, &filename)?);
+                    }
+                },
+                EmulatorEvent::Pause => {
+                    paused = !paused;
+                    run_frame = !paused;
+                }
+                EmulatorEvent::SpeedUp => {
+                    ipf = (ipf + (ipf / 4).max(1)).min(MAX_INSTRUCTIONS_PER_FRAME);
+                    overlay.info(&format!("speed {} ipf", ipf));
+                }
+                EmulatorEvent::SpeedDown => {
+                    ipf = ipf.saturating_sub((ipf / 5).max(1)).max(1);
+                    overlay.info(&format!("speed {} ipf", ipf));
+                }
+                EmulatorEvent::FrameAdvance => {
+                    if paused && !menu_open {
+                        run_frame = true;
+                    }
+                }
+                EmulatorEvent::InstructionStep => {
+                    if paused {
+                        overlay.info(&cpu.current_instruction());
+                        match cpu.cycle(&key_state) {
+                            Ok(output) => needs_render |= output.redraw_flag,
+                            Err(e) => {
+                                save_before_fault(&config, &mut cpu, trace.take(), program.len())?;
+                                return Err(e);
+                            }
+                        }
+                    }
+                }
+                EmulatorEvent::Menu => {
+                    overlay.open_menu(&config);
+                    run_frame = false;
+                }
+                EmulatorEvent::Stats => overlay.toggle_stats(),
+                EmulatorEvent::SaveState => {
+                    saved_state = Some((cpu.clone(), frames.clone()));
+                    overlay.info("state saved");
+                }
+                EmulatorEvent::LoadState => match &saved_state {
+                    Some((state, saved_frames)) => {
+                        // quirks changed since the save still apply
+                        let mut state = state.clone();
+                        state.quirks = config.cpu.quirks;
+                        keep_instrumentation(&mut cpu, &mut state);
+                        cpu = state;
+                        frames = saved_frames.clone();
+                        if cpu.audio_pattern_loaded {
+                            audio_driver.set_pattern(&cpu.audio_pattern, cpu.pitch);
+                        } else {
+                            audio_driver.clear_pattern();
+                        }
+                        needs_render = true;
+                        overlay.info("state loaded");
+                    }
+                    None => overlay.error("no saved state"),
+                },
+            }
+        }
+
+        if reload {
+            let reloaded = read_program(&config.filename).and_then(|new_program| {
+                reset(&mut cpu, audio_driver, &new_program, &config.cpu)?;
+                Ok(new_program)
+            });
+
+            match reloaded {
+                Ok(new_program) => {
+                    program = new_program;
+                    match &replay_movie {
+                        Some(movie) => {
+                            // a program that faults starts over instead
+                            if let Err(e) = replay(&mut cpu, movie, &frames) {
+                                overlay.error(&e.to_string());
+                                reset(&mut cpu, audio_driver, &program, &config.cpu)?;
+                                frames = FrameHistory::default();
+                            }
+                        }
+                        None => frames = FrameHistory::default(),
+                    }
+                    saved_state = None;
+                    needs_render = true;
+                    overlay.info(&format!("reloaded '{}'", config.filename));
+                }
+                Err(e) => overlay.error(&format!("could not reload '{}': {}", config.filename, e)),
+            }
+        }
+
+        if run_frame {
+            let frame_output = match cpu.run_frame(&key_state, ipf) {
+                Ok(output) => output,
+                Err(e) => {
+                    save_before_fault(&config, &mut cpu, trace.take(), program.len())?;
+                    return Err(e);
+                }
+            };
+            frames.push(ipf);
+            overlay.count_frame();
+            needs_render |= frame_output.redraw_flag;
+
+            // a pitch set before any pattern would play an empty one
+            if frame_output.audio_flag && cpu.audio_pattern_loaded {
+                audio_driver.set_pattern(&cpu.audio_pattern, cpu.pitch);
+            }
+
+            if frame_output.sound_flag {
+                audio_driver.start_beep();
+            } else {
+                audio_driver.stop_beep();
+            }
+
+            if let Some(recorder) = &mut video_recorder {
+                recorder.add_frame(&cpu.display)?;
+            }
+        } else {
+            audio_driver.stop_beep();
+        }
+
+        if let Some(recorder) = &mut audio_recorder {
+            recorder.write(&audio_driver.take_recording())?;
+        }
+
+        if let Some(trace) = &mut trace {
+            trace.write(&mut cpu)?;
+        }
+
+        // fast forward runs frames as fast as it can, but the window is only
+        // drawn at the normal rate since presenting waits for vsync
+        needs_render |= overlay.update();
+        let render_interval = Duration::from_secs(1) / FRAME_RATE;
+        if needs_render && (!fast_forward || last_render.elapsed() >= render_interval) {
+            display_driver.clear();
+            display_driver.draw_display(&cpu.display)?;
+            overlay.draw(display_driver, ipf)?;
+            display_driver.present();
+            needs_render = false;
+            last_render = Instant::now();
+        }
+
+        if let Some(rate) = fast_loop_helper.report_rate() {
+            fast_forward_speed = rate / FRAME_RATE as f64;
+        }
+
+        let new_title = window_title(
+            &config,
+            ipf,
+            paused || overlay.menu_open(),
+            fast_forward,
+            fast_forward_speed,
+        );
+        if new_title != title {
+            display_driver.set_title(&new_title)?;
+            title = new_title;
+        }
+
+        if !fast_forward {
+            loop_helper.loop_sleep();
+        }
+    }
+}
+
+// the instructions per frame of each frame since the program started, as runs
+// of frames at the same speed, so the input movie can be replayed exactly
+#[derive(Clone, Default)]
+struct FrameHistory {
+    runs: Vec<(u32, u32)>, // instructions per frame and number of frames
+}
+
+impl FrameHistory {
+    fn push(&mut self, ipf: u32) {
+        match self.runs.last_mut() {
+            Some((run_ipf, count)) if *run_ipf == ipf => *count += 1,
+            _ => self.runs.push((ipf, 1)),
+        }
+    }
+
+    fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.runs
+            .iter()
+            .flat_map(|&(ipf, count)| iter::repeat_n(ipf, count as usize))
+    }
+}
+
+// run the input movie through a new cpu up to the frame the old one reached,
+// at the speed each frame originally ran at
+fn replay(
+    cpu: &mut Cpu,
+    movie: &InputMovie,
+    frames: &FrameHistory,
+) -> Result<(), Box<dyn error::Error>> {
+    for (frame, ipf) in frames.iter().enumerate() {
+        cpu.run_frame(&movie.key_state(frame as u32), ipf)
+            .map_err(|e| format!("replay stopped at frame {}: {}", frame, e))?;
+    }
+    Ok(())
+}
+
+// set up the drivers for a program's settings. on an error they are left as
+// they were: the keymap is checked first, and the audio settings only change
+// once the beep sample has loaded
+fn configure_drivers(
+    display_driver: &mut DisplayDriver,
+    audio_driver: &mut AudioDriver,
+    input_driver: &mut InputDriver,
+    config: &Config,
+) -> Result<(), Box<dyn error::Error>> {
+    InputDriver::check_keymap(&config.keymap, &config.key_hints)?;
+    audio_driver.set_settings(&config.audio)?;
+    input_driver.set_keymap(&config.keymap, &config.key_hints)?;
+    if config.mute != audio_driver.is_muted() {
+        audio_driver.toggle_mute();
+    }
+    display_driver.set_palette(&config.palette);
+    Ok(())
+}
+
+// start the program over with a new cpu, the old one is kept if the program
+// does not fit
+fn reset(
+    cpu: &mut Cpu,
+    audio_driver: &mut AudioDriver,
+    program: &[u8],
+    options: &CpuOptions,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut new_cpu = Cpu::with_options(program, options)?;
+    keep_instrumentation(cpu, &mut new_cpu);
+    *cpu = new_cpu;
+    audio_driver.stop_beep();
+    audio_driver.clear_pattern();
+    Ok(())
+}
+
+// the profile, coverage and trace carry on when the cpu is replaced
+fn keep_instrumentation(old: &mut Cpu, new: &mut Cpu) {
+    new.profiler = old.profiler.take();
+    new.coverage = old.coverage.take();
+    new.tracer = old.tracer.take();
+}
+
+// rom title, speed and state for the window bar
+fn window_title(
+    config: &Config,
+    ipf: u32,
+    paused: bool,
+    fast_forward: bool,
+    fast_forward_speed: f64,
+) -> String {
+    let mut title = String::from("chip8");
+
+    if let Some(rom_title) = &config.title {
+        title += &format!(" - {}", rom_title);
+    }
+
+    title += &format!(" - {} ipf", ipf);
+
+    if paused {
+        title += " - paused";
+    } else if fast_forward {
+        title += &format!(" - fast forward x{:.1}", fast_forward_speed);
+    }
+
+    title
+}
+
+fn new_screenshotter(config: &Config) -> Result<Screenshotter, Box<dyn error::Error>> {
+    let scale = if config.screenshot_native {
+        1
+    } else {
+        config.scale
+    };
+    Screenshotter::new(&config.palette, scale, config.screenshot_clipboard)
+}
+
+fn start_audio_recording(
+    audio_driver: &mut AudioDriver,
+    overlay: &mut Overlay,
+    filename: &str,
+) -> Result<AudioRecorder, Box<dyn error::Error>> {
+    let recorder = AudioRecorder::new(filename, audio_driver.sample_rate())?;
+    audio_driver.start_recording();
+    overlay.info(&format!("recording audio to '{}'", filename));
+    Ok(recorder)
+}
+
+fn start_video_recording(
+    config: &Config,
+    overlay: &mut Overlay,
+    filename: &str,
+) -> Result<VideoRecorder, Box<dyn error::Error>> {
+    let recorder = VideoRecorder::new(filename, &config.palette, VIDEO_SCALE)?;
+    overlay.info(&format!("recording video to '{}'", filename));
+    Ok(recorder)
+}
+
+fn stop_video_recording(
+    overlay: &mut Overlay,
+    recorder: VideoRecorder,
+) -> Result<(), Box<dyn error::Error>> {
+    overlay.info(&format!("saved video recording '{}'", recorder.filename()));
+    recorder.finish()
+}
+
+fn stop_audio_recording(
+    audio_driver: &mut AudioDriver,
+    overlay: &mut Overlay,
+    mut recorder: AudioRecorder,
+) -> Result<(), Box<dyn error::Error>> {
+    recorder.write(&audio_driver.stop_recording())?;
+    overlay.info(&format!("saved audio recording '{}'", recorder.filename()));
+    recorder.finish()
+}
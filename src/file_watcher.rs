@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// polls a file for changes. a change is only reported once the file has stopped
+// changing for a poll interval, so a rom that is still being written by a build
+// is not loaded half finished
+pub struct FileWatcher {
+    path: PathBuf,
+    last_poll: Instant,
+    last_seen: Option<(SystemTime, u64)>, // modified time and length
+    pending: bool,
+}
+
+impl FileWatcher {
+    pub fn new(path: &str) -> Self {
+        let path = PathBuf::from(path);
+        let last_seen = file_state(&path);
+
+        FileWatcher {
+            path,
+            last_poll: Instant::now(),
+            last_seen,
+            pending: false,
+        }
+    }
+
+    pub fn changed(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let state = file_state(&self.path);
+        if state != self.last_seen {
+            self.last_seen = state;
+            self.pending = true;
+            return false;
+        }
+
+        // a missing file is usually being replaced, wait for it to come back
+        if self.pending && state.is_some() {
+            self.pending = false;
+            return true;
+        }
+
+        false
+    }
+}
+
+fn file_state(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
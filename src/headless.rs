@@ -0,0 +1,97 @@
+use crate::config::Config;
+use crate::input_movie::InputMovie;
+use crate::io::load_beep_sample;
+use crate::io::Buzzer;
+use crate::io::SAMPLE_RATE;
+use crate::processor::Cpu;
+use crate::processor::TraceFile;
+use crate::recording::AudioRecorder;
+use crate::recording::VideoRecorder;
+use crate::FRAME_RATE;
+use crate::VIDEO_SCALE;
+use crate::{save_before_fault, save_reports};
+use std::error;
+
+// run a program for a fixed number of frames without opening a window or an
+// audio device, recordings are generated from the emulated timers directly
+pub fn run(config: &Config, program: &[u8]) -> Result<(), Box<dyn error::Error>> {
+    let mut cpu = Cpu::with_options(program, &config.cpu)?;
+
+    let mut trace = match &config.trace {
+        Some(filename) => Some(TraceFile::start(filename, &mut cpu, &config.trace_options)?),
+        None => None,
+    };
+
+    let input_movie = match &config.input_movie {
+        Some(filename) => Some(InputMovie::from_file(filename)?),
+        None => None,
+    };
+
+    let mut audio = match &config.record_audio {
+        Some(filename) => {
+            let samples = load_beep_sample(&config.audio.beep, SAMPLE_RATE)?;
+            let mut buzzer = Buzzer::new(&config.audio, SAMPLE_RATE, samples);
+            buzzer.muted = config.mute;
+            Some((buzzer, AudioRecorder::new(filename, SAMPLE_RATE)?))
+        }
+        None => None,
+    };
+
+    let mut video_recorder = match &config.record_video {
+        Some(filename) => Some(VideoRecorder::new(filename, &config.palette, VIDEO_SCALE)?),
+        None => None,
+    };
+
+    // 44100 / 60 divides evenly, so every frame gets exactly the same number of samples
+    let mut frame_samples = vec![0.0; (SAMPLE_RATE as u32 / FRAME_RATE) as usize];
+
+    for frame in 0..config.frames {
+        let key_state = match &input_movie {
+            Some(movie) => movie.key_state(frame),
+            None => [false; 16],
+        };
+
+        let frame_output = match cpu.run_frame(&key_state, config.instructions_per_frame) {
+            Ok(output) => output,
+            Err(e) => {
+                save_before_fault(config, &mut cpu, trace.take(), program.len())?;
+                return Err(e);
+            }
+        };
+
+        if let Some(trace) = &mut trace {
+            trace.write(&mut cpu)?;
+        }
+
+        if let Some((buzzer, recorder)) = &mut audio {
+            // a pitch set before any pattern would play an empty one
+            if frame_output.audio_flag && cpu.audio_pattern_loaded {
+                buzzer.set_pattern(&cpu.audio_pattern, cpu.pitch);
+            }
+
+            buzzer.gate = frame_output.sound_flag;
+            buzzer.generate(&mut frame_samples);
+            recorder.write(&frame_samples)?;
+        }
+
+        if let Some(recorder) = &mut video_recorder {
+            recorder.add_frame(&cpu.display)?;
+        }
+    }
+
+    if let Some((_, recorder)) = audio {
+        recorder.finish()?;
+    }
+
+    if let Some(recorder) = video_recorder {
+        recorder.finish()?;
+    }
+
+    if let Some(trace) = trace {
+        trace.finish()?;
+    }
+
+    save_reports(config, &cpu, program.len())?;
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use crate::config::Config;
+use crate::headless;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// V0 = 1, V0 += 1, then an instruction that doesn't exist
+const FAULTING_PROGRAM: [u8; 6] = [0x60, 0x01, 0x70, 0x01, 0xff, 0xff];
+
+// a directory of the test's own for the files a run writes
+fn setup(test: &str) -> (Config, PathBuf) {
+    let dir = env::temp_dir().join(format!("chip8-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let mut config = Config::new("fault.ch8");
+    config.headless = true;
+    config.frames = 5;
+    (config, dir)
+}
+
+fn file(dir: &PathBuf, name: &str) -> Option<String> {
+    Some(dir.join(name).to_str()?.to_string())
+}
+
+#[test]
+fn test_trace_fault() {
+    let (mut config, dir) = setup("headless_trace_fault");
+    config.trace = file(&dir, "trace.txt");
+
+    let result = headless::run(&config, &FAULTING_PROGRAM);
+    let trace = fs::read_to_string(dir.join("trace.txt")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(result
+        .unwrap_err()
+        .to_string()
+        .contains("Unknown instruction"));
+    // the header, then every instruction up to and including the fault
+    let lines: Vec<&str> = trace.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].starts_with('#'));
+    assert!(lines[3].contains(" 204 ffff "));
+}
+
+#[test]
+fn test_profile_fault() {
+    let (mut config, dir) = setup("headless_profile_fault");
+    config.profile = file(&dir, "profile.txt");
+    config.cpu.profile = true;
+
+    let result = headless::run(&config, &FAULTING_PROGRAM);
+    let profile = fs::read_to_string(dir.join("profile.txt"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(result.is_err());
+    // the faulting instruction was counted before it failed
+    assert!(profile.unwrap().contains("ffff"));
+}
+
+#[test]
+fn test_coverage_fault() {
+    let (mut config, dir) = setup("headless_coverage_fault");
+    config.coverage = file(&dir, "coverage.info");
+    config.heatmap = file(&dir, "heatmap.png");
+    config.cpu.coverage = true;
+
+    let result = headless::run(&config, &FAULTING_PROGRAM);
+    let coverage = fs::read_to_string(dir.join("coverage.info"));
+    let listing = fs::read_to_string(dir.join("coverage.asm"));
+    let heatmap = fs::metadata(dir.join("heatmap.png"));
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(result.is_err());
+    // the three instructions ran once each, the last one faulting
+    let coverage = coverage.unwrap();
+    assert_eq!(coverage.matches("DA:").count(), 3);
+    assert!(!coverage.contains(",0\n"));
+    assert!(listing.unwrap().contains("ffff"));
+    assert!(heatmap.unwrap().len() > 0);
+}
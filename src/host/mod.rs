@@ -0,0 +1,47 @@
+mod sdl;
+
+use crate::processor::{Cpu, FrameOutput, Framebuffer};
+use std::error;
+
+pub use self::sdl::{open_sdl, SdlDisplay, SdlInput, SdlTimer};
+
+pub const FRAME_RATE: f64 = 60.0;
+
+// where the display goes when an instruction changed it
+pub trait Display {
+    fn show(&mut self, display: &Framebuffer) -> Result<(), Box<dyn error::Error>>;
+}
+
+// the keypad, read once per frame. None once the user asked to quit
+pub trait Input {
+    fn keys(&mut self) -> Option<[bool; 16]>;
+}
+
+// paces the frames at 60hz and plays the beep while the sound timer runs
+pub trait Timer {
+    fn set_beep(&mut self, on: bool);
+    fn wait_for_frame(&mut self);
+}
+
+// run frames until the input asks to stop. run_frame is either the
+// interpreter's Cpu::run_frame or the run_frame of a recompiled program
+pub fn run<F>(
+    cpu: &mut Cpu,
+    display: &mut impl Display,
+    input: &mut impl Input,
+    timer: &mut impl Timer,
+    mut run_frame: F,
+) -> Result<(), Box<dyn error::Error>>
+where
+    F: FnMut(&mut Cpu, &[bool; 16]) -> Result<FrameOutput, Box<dyn error::Error>>,
+{
+    while let Some(keys) = input.keys() {
+        let output = run_frame(cpu, &keys)?;
+        if output.redraw_flag {
+            display.show(&cpu.display)?;
+        }
+        timer.set_beep(output.sound_flag);
+        timer.wait_for_frame();
+    }
+    Ok(())
+}
@@ -0,0 +1,178 @@
+use super::{Display, Input, Timer, FRAME_RATE};
+use crate::processor::{pixel, Framebuffer, CHIP8_HEIGHT, CHIP8_WIDTH};
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+use spin_sleep::LoopHelper;
+use std::error;
+
+// 1 2 3 C        1 2 3 4
+// 4 5 6 D   <-   Q W E R
+// 7 8 9 E        A S D F
+// A 0 B F        Z X C V
+const KEYMAP: [Keycode; 16] = [
+    Keycode::X,
+    Keycode::Num1,
+    Keycode::Num2,
+    Keycode::Num3,
+    Keycode::Q,
+    Keycode::W,
+    Keycode::E,
+    Keycode::A,
+    Keycode::S,
+    Keycode::D,
+    Keycode::Z,
+    Keycode::C,
+    Keycode::Num4,
+    Keycode::R,
+    Keycode::F,
+    Keycode::V,
+];
+
+const BEEP_FREQUENCY: f32 = 440.0;
+const BEEP_VOLUME: f32 = 0.25;
+
+// a plain window, keyboard and square wave beep, for programs that don't need
+// the emulator's menus, palettes and recording
+pub fn open_sdl(
+    title: &str,
+    scale: u32,
+) -> Result<(SdlDisplay, SdlInput, SdlTimer), Box<dyn error::Error>> {
+    let sdl_context = sdl2::init()?;
+
+    let window = sdl_context
+        .video()?
+        .window(title, CHIP8_WIDTH * scale, CHIP8_HEIGHT * scale)
+        .position_centered()
+        .build()?;
+    let mut canvas = window.into_canvas().build()?;
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    canvas.clear();
+    canvas.present();
+
+    let desired_spec = AudioSpecDesired {
+        freq: Some(44100),
+        channels: Some(1),
+        samples: None,
+    };
+    let device = sdl_context
+        .audio()?
+        .open_playback(None, &desired_spec, |spec| SquareWave {
+            step: BEEP_FREQUENCY / spec.freq as f32,
+            phase: 0.0,
+            on: false,
+        })?;
+    device.resume();
+
+    Ok((
+        SdlDisplay { canvas, scale },
+        SdlInput {
+            event_pump: sdl_context.event_pump()?,
+        },
+        SdlTimer {
+            device,
+            loop_helper: LoopHelper::builder().build_with_target_rate(FRAME_RATE),
+        },
+    ))
+}
+
+pub struct SdlDisplay {
+    canvas: Canvas<Window>,
+    scale: u32, // window pixels per chip8 pixel
+}
+
+impl Display for SdlDisplay {
+    fn show(&mut self, display: &Framebuffer) -> Result<(), Box<dyn error::Error>> {
+        let mut rects = Vec::<Rect>::new();
+        let scale = self.scale;
+
+        for x in 0..CHIP8_WIDTH {
+            for y in 0..CHIP8_HEIGHT {
+                if pixel(display, x as usize, y as usize) {
+                    rects.push(Rect::new(
+                        (x * scale) as i32,
+                        (y * scale) as i32,
+                        scale,
+                        scale,
+                    ));
+                }
+            }
+        }
+
+        self.canvas.set_draw_color(Color::RGB(0, 0, 0));
+        self.canvas.clear();
+        self.canvas.set_draw_color(Color::RGB(255, 255, 255));
+        self.canvas.fill_rects(&rects)?;
+        self.canvas.present();
+        Ok(())
+    }
+}
+
+pub struct SdlInput {
+    event_pump: EventPump,
+}
+
+impl Input for SdlInput {
+    fn keys(&mut self) -> Option<[bool; 16]> {
+        for event in self.event_pump.poll_iter() {
+            match event {
+                Event::Quit { .. }
+                | Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    ..
+                } => return None,
+                _ => {}
+            }
+        }
+
+        let pressed: Vec<Keycode> = self
+            .event_pump
+            .keyboard_state()
+            .pressed_scancodes()
+            .filter_map(Keycode::from_scancode)
+            .collect();
+        Some(KEYMAP.map(|key| pressed.contains(&key)))
+    }
+}
+
+pub struct SdlTimer {
+    device: AudioDevice<SquareWave>,
+    loop_helper: LoopHelper,
+}
+
+impl Timer for SdlTimer {
+    fn set_beep(&mut self, on: bool) {
+        self.device.lock().on = on;
+    }
+
+    fn wait_for_frame(&mut self) {
+        self.loop_helper.loop_sleep();
+        self.loop_helper.loop_start();
+    }
+}
+
+pub struct SquareWave {
+    step: f32,  // fraction of a period per sample
+    phase: f32, // 0.0 - 1.0
+    on: bool,
+}
+
+impl AudioCallback for SquareWave {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        for sample in out.iter_mut() {
+            *sample = match (self.on, self.phase < 0.5) {
+                (false, _) => 0.0,
+                (true, true) => BEEP_VOLUME,
+                (true, false) => -BEEP_VOLUME,
+            };
+            self.phase = (self.phase + self.step) % 1.0;
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::error;
+use std::fs;
+
+// keypad input for headless runs, read from a text file with one entry per line:
+//
+//     <frame> <keys held from that frame on, as hex digits, or - for none>
+//
+// for example `120 5 6` holds keys 5 and 6 from frame 120 until the next entry.
+// blank lines and lines starting with # are ignored
+pub struct InputMovie {
+    entries: Vec<(u32, [bool; 16])>,
+}
+
+impl InputMovie {
+    pub fn from_file(filename: &str) -> Result<Self, Box<dyn error::Error>> {
+        let text = fs::read_to_string(filename)
+            .map_err(|e| format!("could not read '{}': {}", filename, e))?;
+
+        Self::parse(&text).map_err(|e| format!("{}: {}", filename, e).into())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut entries: Vec<(u32, [bool; 16])> = Vec::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let frame = words
+                .next()
+                .and_then(|w| w.parse::<u32>().ok())
+                .ok_or(format!("line {}: expected a frame number", number + 1))?;
+
+            if let Some((last, _)) = entries.last() {
+                if frame < *last {
+                    return Err(format!("line {}: frames must be in order", number + 1));
+                }
+            }
+
+            let mut key_state = [false; 16];
+            for word in words {
+                if word == "-" {
+                    continue;
+                }
+                match u8::from_str_radix(word, 16) {
+                    Ok(key) if key < 16 => key_state[key as usize] = true,
+                    _ => return Err(format!("line {}: invalid key '{}'", number + 1, word)),
+                }
+            }
+
+            entries.push((frame, key_state));
+        }
+
+        Ok(InputMovie { entries })
+    }
+
+    // keys held during the given frame
+    pub fn key_state(&self, frame: u32) -> [bool; 16] {
+        self.entries
+            .iter()
+            .take_while(|(start, _)| *start <= frame)
+            .last()
+            .map(|(_, key_state)| *key_state)
+            .unwrap_or([false; 16])
+    }
+}
@@ -0,0 +1,48 @@
+use crate::input_movie::InputMovie;
+
+fn keys(held: &[usize]) -> [bool; 16] {
+    let mut key_state = [false; 16];
+    for &key in held {
+        key_state[key] = true;
+    }
+    key_state
+}
+
+#[test]
+fn test_parse() {
+    let text = "# start the game\n\n0 5\n  120 5 a  \n300 -\n300 F\n";
+    let movie = InputMovie::parse(text).unwrap();
+
+    assert_eq!(movie.key_state(0), keys(&[5]));
+    assert_eq!(movie.key_state(119), keys(&[5]));
+    assert_eq!(movie.key_state(120), keys(&[5, 0xa]));
+    // the last entry for a frame wins
+    assert_eq!(movie.key_state(300), keys(&[0xf]));
+    assert_eq!(movie.key_state(1000), keys(&[0xf]));
+}
+
+#[test]
+fn test_parse_empty() {
+    let movie = InputMovie::parse("# nothing pressed\n").unwrap();
+    assert_eq!(movie.key_state(0), keys(&[]));
+
+    // no keys are held before the first entry
+    let movie = InputMovie::parse("60 1").unwrap();
+    assert_eq!(movie.key_state(59), keys(&[]));
+}
+
+#[test]
+fn test_parse_errors() {
+    let errors = [
+        ("# header\n1.5 2", "line 2: expected a frame number"),
+        ("0 1\nx 1", "line 2: expected a frame number"),
+        ("-1 1", "line 1: expected a frame number"),
+        ("10 1\n5 2", "line 2: frames must be in order"),
+        ("0 10", "line 1: invalid key '10'"),
+        ("0 g", "line 1: invalid key 'g'"),
+    ];
+
+    for (text, error) in errors {
+        assert_eq!(InputMovie::parse(text).err().as_deref(), Some(error));
+    }
+}
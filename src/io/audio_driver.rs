@@ -1,51 +1,166 @@
-use sdl2;
-use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use super::buzzer::{AudioSettings, BeepSound, Buzzer, SAMPLE_RATE};
+use sdl2::audio::{
+    AudioCVT, AudioCallback, AudioDevice, AudioFormat, AudioSpecDesired, AudioSpecWAV,
+};
+use sdl2::rwops::RWops;
 use std::error;
 
-pub struct AudioDriver {
-    device: AudioDevice<SquareWave>,
-}
+const BUNDLED_BEEP: &[u8] = include_bytes!("../../res/beep.wav");
 
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volume: f32,
+pub struct AudioDriver {
+    device: AudioDevice<Buzzer>,
+    beeping: bool,
+    muted: bool,
 }
 
-impl AudioCallback for SquareWave {
+impl AudioCallback for Buzzer {
     type Channel = f32;
 
     fn callback(&mut self, out: &mut [f32]) {
-        // Generate a square wave
-        for x in out.iter_mut() {
-            *x = self.volume * if self.phase < 0.5 { 1.0 } else { -1.0 };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
-        }
+        self.generate(out);
     }
 }
 
 impl AudioDriver {
-    pub fn new(sdl_context: &sdl2::Sdl) -> Result<AudioDriver, Box<dyn error::Error>> {
+    pub fn new(
+        sdl_context: &sdl2::Sdl,
+        settings: &AudioSettings,
+    ) -> Result<AudioDriver, Box<dyn error::Error>> {
         let audio_subsystem = sdl_context.audio()?;
         let desired_spec = AudioSpecDesired {
-            freq: Some(44100),
+            freq: Some(SAMPLE_RATE),
             channels: Some(1),
             samples: None,
         };
-        let device = audio_subsystem.open_playback(None, &desired_spec, |spec| SquareWave {
-            phase_inc: 240.0 / spec.freq as f32,
-            phase: 0.0,
-            volume: 0.25,
+
+        // the device rate is only known once the device is open, so the sample
+        // is resampled inside the callback constructor
+        let mut sample_error = None;
+        let device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
+            match load_beep_sample(&settings.beep, spec.freq) {
+                Ok(samples) => Buzzer::new(settings, spec.freq, samples),
+                Err(e) => {
+                    sample_error = Some(e);
+                    Buzzer::new(settings, spec.freq, None)
+                }
+            }
         })?;
 
-        Ok(Self { device: device })
+        if let Some(e) = sample_error {
+            return Err(e);
+        }
+
+        device.resume();
+
+        Ok(Self {
+            device,
+            beeping: false,
+            muted: false,
+        })
+    }
+
+    // replace the tone and envelope, keeping the mute state and any recording
+    pub fn set_settings(&mut self, settings: &AudioSettings) -> Result<(), Box<dyn error::Error>> {
+        let freq = self.sample_rate();
+        let mut buzzer = Buzzer::new(settings, freq, load_beep_sample(&settings.beep, freq)?);
+
+        let mut current = self.device.lock();
+        buzzer.muted = current.muted;
+        buzzer.recording = current.recording.take();
+        *current = buzzer;
+
+        self.beeping = false;
+        Ok(())
+    }
+
+    pub fn sample_rate(&self) -> i32 {
+        self.device.spec().freq
+    }
+
+    pub fn start_beep(&mut self) {
+        if !self.beeping {
+            self.beeping = true;
+            self.device.lock().gate = true;
+        }
+    }
+
+    pub fn stop_beep(&mut self) {
+        if self.beeping {
+            self.beeping = false;
+            self.device.lock().gate = false;
+        }
     }
 
-    pub fn start_beep(&self) {
-        self.device.resume();
+    // hand a new xo-chip pattern and pitch over to the audio thread, the device
+    // lock keeps the callback from running while it is replaced
+    pub fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        self.device.lock().set_pattern(pattern, pitch);
     }
 
-    pub fn stop_beep(&self) {
-        self.device.pause();
+    pub fn clear_pattern(&mut self) {
+        self.device.lock().clear_pattern();
     }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        self.device.lock().muted = self.muted;
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    // keep a copy of everything the callback plays from now on
+    pub fn start_recording(&mut self) {
+        self.device.lock().recording = Some(Vec::new());
+    }
+
+    // samples played since the last call
+    pub fn take_recording(&mut self) -> Vec<f32> {
+        match &mut self.device.lock().recording {
+            Some(recording) => std::mem::take(recording),
+            None => Vec::new(),
+        }
+    }
+
+    // samples played since the last call, no more are kept afterwards
+    pub fn stop_recording(&mut self) -> Vec<f32> {
+        self.device.lock().recording.take().unwrap_or_default()
+    }
+}
+
+// load the beep sample as mono f32 samples at the given rate, none if a tone is used
+pub fn load_beep_sample(
+    beep: &BeepSound,
+    freq: i32,
+) -> Result<Option<Vec<f32>>, Box<dyn error::Error>> {
+    let wav = match beep {
+        BeepSound::Tone => return Ok(None),
+        BeepSound::BundledSample => {
+            AudioSpecWAV::load_wav_rw(&mut RWops::from_bytes(BUNDLED_BEEP)?)?
+        }
+        BeepSound::Sample(filename) => AudioSpecWAV::load_wav(filename)
+            .map_err(|e| format!("could not load '{}': {}", filename, e))?,
+    };
+
+    let cvt = AudioCVT::new(
+        wav.format,
+        wav.channels,
+        wav.freq,
+        AudioFormat::f32_sys(),
+        1,
+        freq,
+    )?;
+
+    let samples: Vec<f32> = cvt
+        .convert(wav.buffer().to_vec())
+        .chunks_exact(4)
+        .map(|b| f32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        .collect();
+
+    if samples.is_empty() {
+        return Err("beep sample contains no audio".into());
+    }
+
+    Ok(Some(samples))
 }
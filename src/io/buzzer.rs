@@ -0,0 +1,270 @@
+use std::f32::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+
+pub const SAMPLE_RATE: i32 = 44100;
+
+// the sound played while the sound timer is active
+#[derive(Clone)]
+pub enum BeepSound {
+    Tone,
+    BundledSample,
+    Sample(String),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Waveform {
+    Square,
+    Sine,
+    Triangle,
+    Noise,
+}
+
+#[derive(Clone)]
+pub struct AudioSettings {
+    pub beep: BeepSound,
+    pub waveform: Waveform, // tone waveform, unused for samples
+    pub frequency: f32,     // tone frequency in hz, unused for samples
+    pub volume: f32,        // 0.0 - 1.0
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+// generates the buzzer signal, either inside the sdl audio callback or directly
+// when running headless. the beep is gated rather than the output paused, and
+// faded in and out with a linear envelope to avoid clicks
+pub struct Buzzer {
+    source: Source,
+    pattern: Option<PatternPlayer>, // replaces the source once a rom loads a pattern
+    freq: i32,
+    pub gate: bool,
+    pub muted: bool,
+    level: f32,
+    attack_step: f32,
+    release_step: f32,
+    volume: f32,
+    pub recording: Option<Vec<f32>>, // generated samples not yet taken by a recorder
+}
+
+enum Source {
+    Tone(Oscillator),
+    Sample(SampleLoop),
+}
+
+struct Oscillator {
+    waveform: Waveform,
+    phase_inc: f32,
+    phase: f32,
+    noise: u16, // lfsr state, a new noise value is taken every period
+    noise_value: f32,
+}
+
+// a wav sample converted to the device format, looped for as long as the beep lasts
+struct SampleLoop {
+    samples: Vec<f32>,
+    position: usize,
+}
+
+// xo-chip 128 sample 1-bit pattern, looped at a rate set by the pitch register
+struct PatternPlayer {
+    pattern: [u8; 16],
+    phase_inc: f32, // pattern samples per device sample
+    phase: f32,
+}
+
+impl Buzzer {
+    // samples are the converted beep sample, if one is used instead of a tone
+    pub fn new(settings: &AudioSettings, freq: i32, samples: Option<Vec<f32>>) -> Self {
+        let source = match samples {
+            Some(samples) => Source::Sample(SampleLoop {
+                samples,
+                position: 0,
+            }),
+            None => Source::Tone(Oscillator::new(settings.waveform, settings.frequency, freq)),
+        };
+
+        Buzzer {
+            source,
+            pattern: None,
+            freq,
+            gate: false,
+            muted: false,
+            level: 0.0,
+            attack_step: envelope_step(settings.attack_ms, freq),
+            release_step: envelope_step(settings.release_ms, freq),
+            volume: settings.volume.clamp(0.0, 1.0),
+            recording: None,
+        }
+    }
+
+    pub fn generate(&mut self, out: &mut [f32]) {
+        for x in out.iter_mut() {
+            if self.gate && !self.muted {
+                self.level = (self.level + self.attack_step).min(1.0);
+            } else {
+                self.level = (self.level - self.release_step).max(0.0);
+            }
+
+            *x = if self.level > 0.0 {
+                let sample = match &mut self.pattern {
+                    Some(pattern) => pattern.next_sample(),
+                    None => self.source.next_sample(),
+                };
+                self.volume * self.level * sample
+            } else {
+                0.0
+            };
+        }
+
+        if let Some(recording) = &mut self.recording {
+            recording.extend_from_slice(out);
+        }
+    }
+
+    pub fn set_pattern(&mut self, pattern: &[u8; 16], pitch: u8) {
+        let phase_inc = pattern_rate(pitch) / self.freq as f32;
+
+        match &mut self.pattern {
+            Some(player) => {
+                player.pattern = *pattern;
+                player.phase_inc = phase_inc;
+            }
+            None => {
+                self.pattern = Some(PatternPlayer {
+                    pattern: *pattern,
+                    phase_inc,
+                    phase: 0.0,
+                })
+            }
+        }
+    }
+
+    // go back to the tone or sample after a program used a pattern
+    pub fn clear_pattern(&mut self) {
+        self.pattern = None;
+    }
+}
+
+impl Source {
+    fn next_sample(&mut self) -> f32 {
+        match self {
+            Source::Tone(oscillator) => oscillator.next_sample(),
+            Source::Sample(sample) => sample.next_sample(),
+        }
+    }
+}
+
+impl Oscillator {
+    fn new(waveform: Waveform, frequency: f32, freq: i32) -> Self {
+        Oscillator {
+            waveform,
+            phase_inc: frequency / freq as f32,
+            phase: 0.0,
+            noise: 0xace1,
+            noise_value: 1.0,
+        }
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let value = match self.waveform {
+            Waveform::Square => {
+                if self.phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Sine => (2.0 * PI * self.phase).sin(),
+            Waveform::Triangle => 4.0 * (self.phase - 0.5).abs() - 1.0,
+            Waveform::Noise => self.noise_value,
+        };
+
+        self.phase += self.phase_inc;
+        if self.phase >= 1.0 {
+            self.phase %= 1.0;
+
+            let bit = (self.noise ^ (self.noise >> 2) ^ (self.noise >> 3) ^ (self.noise >> 5)) & 1;
+            self.noise = (self.noise >> 1) | (bit << 15);
+            self.noise_value = if self.noise & 1 == 1 { 1.0 } else { -1.0 };
+        }
+
+        value
+    }
+}
+
+impl SampleLoop {
+    fn next_sample(&mut self) -> f32 {
+        let value = self.samples[self.position];
+        self.position = (self.position + 1) % self.samples.len();
+        value
+    }
+}
+
+impl PatternPlayer {
+    fn next_sample(&mut self) -> f32 {
+        let position = self.phase as usize;
+        let bit = self.pattern[position / 8] & (0x80 >> (position % 8));
+
+        self.phase = (self.phase + self.phase_inc) % 128.0;
+
+        if bit != 0 {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            beep: BeepSound::Tone,
+            waveform: Waveform::Square,
+            frequency: 240.0,
+            volume: 0.25,
+            attack_ms: 5.0,
+            release_ms: 5.0,
+        }
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "square" => Ok(Waveform::Square),
+            "sine" => Ok(Waveform::Sine),
+            "triangle" => Ok(Waveform::Triangle),
+            "noise" => Ok(Waveform::Noise),
+            _ => Err(format!("unknown waveform '{}'", s)),
+        }
+    }
+}
+
+impl fmt::Display for Waveform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Waveform::Square => "square",
+            Waveform::Sine => "sine",
+            Waveform::Triangle => "triangle",
+            Waveform::Noise => "noise",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// xo-chip pattern playback rate in samples per second
+fn pattern_rate(pitch: u8) -> f32 {
+    4000.0 * 2f32.powf((pitch as f32 - 64.0) / 48.0)
+}
+
+// per sample change in envelope level for a ramp of the given length
+fn envelope_step(ms: f32, freq: i32) -> f32 {
+    let samples = ms / 1000.0 * freq as f32;
+    if samples < 1.0 {
+        1.0
+    } else {
+        1.0 / samples
+    }
+}
@@ -0,0 +1,161 @@
+use super::{AudioSettings, Buzzer, Waveform};
+
+// attack and release of 5 samples at 1000 samples per second, full volume
+fn settings(waveform: Waveform, frequency: f32) -> AudioSettings {
+    AudioSettings {
+        waveform,
+        frequency,
+        volume: 1.0,
+        ..AudioSettings::default()
+    }
+}
+
+fn generate(buzzer: &mut Buzzer, samples: usize) -> Vec<f32> {
+    let mut out = vec![0.0; samples];
+    buzzer.generate(&mut out);
+    out
+}
+
+fn assert_close(actual: &[f32], expected: &[f32]) {
+    assert_eq!(actual.len(), expected.len());
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-4, "{:?} != {:?}", actual, expected);
+    }
+}
+
+#[test]
+fn test_envelope() {
+    // a 1hz square wave stays at 1.0 long enough to show the ramps
+    let mut buzzer = Buzzer::new(&settings(Waveform::Square, 1.0), 1000, None);
+    assert_close(&generate(&mut buzzer, 3), &[0.0; 3]);
+
+    buzzer.gate = true;
+    let attack = generate(&mut buzzer, 7);
+    assert_close(&attack, &[0.2, 0.4, 0.6, 0.8, 1.0, 1.0, 1.0]);
+
+    // released from full level without a jump to 0
+    buzzer.gate = false;
+    let release = generate(&mut buzzer, 5);
+    assert_close(&release, &[0.8, 0.6, 0.4, 0.2, 0.0]);
+    assert!(generate(&mut buzzer, 100).iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn test_release_during_attack() {
+    let mut buzzer = Buzzer::new(&settings(Waveform::Square, 1.0), 1000, None);
+    buzzer.gate = true;
+    generate(&mut buzzer, 2);
+
+    buzzer.gate = false;
+    assert_close(&generate(&mut buzzer, 3), &[0.2, 0.0, 0.0]);
+}
+
+#[test]
+fn test_mute() {
+    let mut buzzer = Buzzer::new(&settings(Waveform::Square, 1.0), 1000, None);
+    buzzer.gate = true;
+    generate(&mut buzzer, 10);
+
+    // muting releases like the end of a beep
+    buzzer.muted = true;
+    assert_close(&generate(&mut buzzer, 5), &[0.8, 0.6, 0.4, 0.2, 0.0]);
+    assert!(generate(&mut buzzer, 100).iter().all(|&x| x == 0.0));
+}
+
+#[test]
+fn test_waveforms() {
+    // a quarter period per sample, with no attack so the level starts at 1
+    let waveforms = [
+        (Waveform::Square, [1.0, 1.0, -1.0, -1.0]),
+        (Waveform::Sine, [0.0, 1.0, 0.0, -1.0]),
+        (Waveform::Triangle, [1.0, 0.0, -1.0, 0.0]),
+    ];
+    for (waveform, expected) in waveforms {
+        let mut settings = settings(waveform, 250.0);
+        settings.attack_ms = 0.0;
+        let mut buzzer = Buzzer::new(&settings, 1000, None);
+        buzzer.gate = true;
+        assert_close(&generate(&mut buzzer, 4), &expected);
+    }
+
+    let mut settings = settings(Waveform::Noise, 250.0);
+    settings.attack_ms = 0.0;
+    let mut buzzer = Buzzer::new(&settings, 1000, None);
+    buzzer.gate = true;
+    let noise = generate(&mut buzzer, 400);
+    assert!(noise.iter().all(|&x| x == 1.0 || x == -1.0));
+    assert!(noise.contains(&1.0) && noise.contains(&-1.0));
+}
+
+#[test]
+fn test_volume() {
+    let mut settings = settings(Waveform::Square, 1.0);
+    settings.attack_ms = 0.0;
+    settings.volume = 0.5;
+    let mut buzzer = Buzzer::new(&settings, 1000, None);
+    buzzer.gate = true;
+    assert_close(&generate(&mut buzzer, 2), &[0.5, 0.5]);
+}
+
+// a buzzer at 4000 samples per second, where pitch 64 plays one pattern bit
+// per sample, with no attack so the pattern comes out as it is
+fn pattern_buzzer() -> Buzzer {
+    let mut settings = settings(Waveform::Square, 240.0);
+    settings.attack_ms = 0.0;
+    let mut buzzer = Buzzer::new(&settings, 4000, None);
+    buzzer.gate = true;
+    buzzer
+}
+
+fn bits(samples: &[f32]) -> Vec<u8> {
+    samples.iter().map(|&x| (x > 0.0) as u8).collect()
+}
+
+#[test]
+fn test_pattern_bit_order() {
+    let mut pattern = [0; 16];
+    pattern[0] = 0b1100_0001;
+    pattern[15] = 0b0000_0011;
+
+    let mut buzzer = pattern_buzzer();
+    buzzer.set_pattern(&pattern, 64);
+    let samples = generate(&mut buzzer, 130);
+
+    // the highest bit of the first byte plays first, then the pattern loops
+    assert!(samples.iter().all(|&x| x == 1.0 || x == -1.0));
+    assert_eq!(bits(&samples[..8]), [1, 1, 0, 0, 0, 0, 0, 1]);
+    assert!(bits(&samples[8..120]).iter().all(|&b| b == 0));
+    assert_eq!(bits(&samples[120..128]), [0, 0, 0, 0, 0, 0, 1, 1]);
+    assert_eq!(bits(&samples[128..]), [1, 1]);
+}
+
+#[test]
+fn test_pattern_pitch() {
+    let mut pattern = [0; 16];
+    pattern[0] = 0b1010_1010;
+
+    // 4000 * 2^((pitch - 64) / 48) pattern bits per second: pitch 64 plays
+    // 4000, 112 twice as many and 16 half as many
+    let mut buzzer = pattern_buzzer();
+    buzzer.set_pattern(&pattern, 64);
+    assert_eq!(bits(&generate(&mut buzzer, 4)), [1, 0, 1, 0]);
+
+    let mut buzzer = pattern_buzzer();
+    buzzer.set_pattern(&pattern, 112);
+    assert_eq!(bits(&generate(&mut buzzer, 4)), [1, 1, 1, 1]);
+
+    let mut buzzer = pattern_buzzer();
+    buzzer.set_pattern(&pattern, 16);
+    assert_eq!(bits(&generate(&mut buzzer, 8)), [1, 1, 0, 0, 1, 1, 0, 0]);
+}
+
+#[test]
+fn test_clear_pattern() {
+    let mut buzzer = pattern_buzzer();
+    buzzer.set_pattern(&[0; 16], 64);
+    assert!(generate(&mut buzzer, 4).iter().all(|&x| x == -1.0));
+
+    // back to the 240hz square wave, which starts high
+    buzzer.clear_pattern();
+    assert_eq!(generate(&mut buzzer, 1), [1.0]);
+}
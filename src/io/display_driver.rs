@@ -1,69 +1,177 @@
-use super::BACKGROUND_COLOR;
+use super::font;
 use super::CHIP8_HEIGHT;
 use super::CHIP8_WIDTH;
-use super::PIXEL_COLOR;
+use crate::palette::Palette;
+use crate::processor::{pixel, Framebuffer};
 use sdl2;
+use sdl2::messagebox::{show_simple_message_box, MessageBoxFlag};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::render::Canvas;
+use sdl2::render::{BlendMode, Canvas};
 use sdl2::video::Window;
 use std::error;
 
 pub struct DisplayDriver {
     canvas: Canvas<Window>,
+    palette: Palette,
+    scale: u32, // window pixels per chip8 pixel
 }
 
 impl DisplayDriver {
-    pub fn new(sdl_context: &sdl2::Sdl) -> Result<DisplayDriver, Box<dyn error::Error>> {
+    pub fn new(
+        sdl_context: &sdl2::Sdl,
+        palette: &Palette,
+        scale: u32,
+    ) -> Result<DisplayDriver, Box<dyn error::Error>> {
         let video_subsystem = sdl_context.video()?;
         let window = video_subsystem
-            .window("chip8", CHIP8_WIDTH * 20, CHIP8_HEIGHT * 20)
+            .window("chip8", CHIP8_WIDTH * scale, CHIP8_HEIGHT * scale)
             .position_centered()
             .build()?;
         let mut canvas = window.into_canvas().present_vsync().build()?;
 
-        canvas.set_scale(20.0, 20.0)?;
+        // text and menus are drawn over the display with translucent panels
+        canvas.set_blend_mode(BlendMode::Blend);
 
-        canvas.set_draw_color(Color::RGB(
-            BACKGROUND_COLOR.0,
-            BACKGROUND_COLOR.1,
-            BACKGROUND_COLOR.2,
-        ));
+        canvas.set_draw_color(rgb(palette.background));
 
         canvas.clear();
         canvas.present();
 
-        Ok(DisplayDriver { canvas: canvas })
+        Ok(DisplayDriver {
+            canvas: canvas,
+            palette: *palette,
+            scale,
+        })
     }
 
-    pub fn render(
-        &mut self,
-        display: &[u8; (CHIP8_HEIGHT * CHIP8_WIDTH) as usize],
-    ) -> Result<(), Box<dyn error::Error>> {
-        let mut rects = Vec::<Rect>::new();
+    // window size in pixels
+    pub fn size(&self) -> (u32, u32) {
+        (CHIP8_WIDTH * self.scale, CHIP8_HEIGHT * self.scale)
+    }
+
+    pub fn palette(&self) -> &Palette {
+        &self.palette
+    }
+
+    pub fn set_title(&mut self, title: &str) -> Result<(), Box<dyn error::Error>> {
+        self.canvas.window_mut().set_title(title)?;
+        Ok(())
+    }
+
+    pub fn set_palette(&mut self, palette: &Palette) {
+        self.palette = *palette;
+    }
+
+    // shown in a message box, blocking until it is closed
+    pub fn show_error(&self, message: &str) {
+        let result = show_simple_message_box(
+            MessageBoxFlag::ERROR,
+            "chip8",
+            message,
+            Some(self.canvas.window()),
+        );
+
+        if let Err(e) = result {
+            eprintln!("could not show message box: {}", e);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.canvas.set_draw_color(rgb(self.palette.background));
         self.canvas.clear();
+    }
+
+    pub fn present(&mut self) {
+        self.canvas.present();
+    }
+
+    pub fn draw_display(&mut self, display: &Framebuffer) -> Result<(), Box<dyn error::Error>> {
+        let mut rects = Vec::<Rect>::new();
+        let scale = self.scale;
 
         for x in 0..CHIP8_WIDTH {
             for y in 0..CHIP8_HEIGHT {
-                if display[(y * CHIP8_WIDTH + x) as usize] == 1 {
-                    rects.push(Rect::new(x as i32, y as i32, 1, 1));
+                if pixel(display, x as usize, y as usize) {
+                    rects.push(Rect::new(
+                        (x * scale) as i32,
+                        (y * scale) as i32,
+                        scale,
+                        scale,
+                    ));
                 }
             }
         }
 
+        self.canvas.set_draw_color(rgb(self.palette.pixel));
+        self.canvas.fill_rects(&rects)?;
+
+        Ok(())
+    }
+
+    // alpha 0 is fully transparent, 255 opaque
+    pub fn fill_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        color: (u8, u8, u8),
+        alpha: u8,
+    ) -> Result<(), Box<dyn error::Error>> {
         self.canvas
-            .set_draw_color(Color::RGB(PIXEL_COLOR.0, PIXEL_COLOR.1, PIXEL_COLOR.2));
+            .set_draw_color(Color::RGBA(color.0, color.1, color.2, alpha));
+        self.canvas.fill_rect(Rect::new(x, y, width, height))?;
+        Ok(())
+    }
 
-        self.canvas.fill_rects(&rects)?;
+    // font pixels are scaled with the window so text stays readable at any size
+    pub fn text_scale(&self) -> u32 {
+        (self.scale / 10).max(1)
+    }
 
-        self.canvas.set_draw_color(Color::RGB(
-            BACKGROUND_COLOR.0,
-            BACKGROUND_COLOR.1,
-            BACKGROUND_COLOR.2,
-        ));
+    pub fn char_width(&self) -> u32 {
+        (font::GLYPH_WIDTH + 1) * self.text_scale()
+    }
 
-        self.canvas.present();
+    pub fn line_height(&self) -> u32 {
+        (font::GLYPH_HEIGHT + 3) * self.text_scale()
+    }
 
+    // text with its top left corner at x, y, on a single line
+    pub fn draw_text(
+        &mut self,
+        x: i32,
+        y: i32,
+        text: &str,
+        color: (u8, u8, u8),
+    ) -> Result<(), Box<dyn error::Error>> {
+        let size = self.text_scale();
+        let mut rects = Vec::<Rect>::new();
+
+        for (i, c) in text.chars().enumerate() {
+            let left = x + (i as u32 * self.char_width()) as i32;
+
+            for (row, bits) in font::glyph(c).iter().enumerate() {
+                for column in 0..font::GLYPH_WIDTH {
+                    if bits & (0x10 >> column) != 0 {
+                        rects.push(Rect::new(
+                            left + (column * size) as i32,
+                            y + (row as u32 * size) as i32,
+                            size,
+                            size,
+                        ));
+                    }
+                }
+            }
+        }
+
+        self.canvas.set_draw_color(rgb(color));
+        self.canvas.fill_rects(&rects)?;
         Ok(())
     }
 }
+
+fn rgb(color: (u8, u8, u8)) -> Color {
+    Color::RGB(color.0, color.1, color.2)
+}
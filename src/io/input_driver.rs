@@ -1,27 +1,140 @@
+use crate::keymap::Keymap;
 use sdl2;
+use sdl2::controller::{Button, GameController};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
+use sdl2::GameControllerSubsystem;
 use std::error;
 
 pub struct InputDriver {
     event_pump: sdl2::EventPump,
+    controller_subsystem: Option<GameControllerSubsystem>,
+    controllers: Vec<GameController>, // kept open so their events are delivered
+    keymap: [Keycode; 16],
+    key_hints: Vec<(Keycode, usize)>, // additional keys for single chip8 keys
+    fast_forward: bool,
+}
+
+// emulator controls, from the window or from keys outside of the chip8 keypad
+#[derive(Clone, PartialEq)]
+pub enum EmulatorEvent {
+    Quit,
+    Back,         // leave the running program
+    Open(String), // a file dropped on the window
+    Reset,
+    Reload,
+    Mute,
+    RecordAudio,
+    RecordVideo,
+    Screenshot,
+    SaveSettings,
+    Pause,
+    SpeedUp,
+    SpeedDown,
+    FrameAdvance,
+    InstructionStep,
+    Menu,
+    Stats,
+    SaveState,
+    LoadState,
+}
+
+// navigation in the launcher, from the keyboard or a game controller
+#[derive(Clone, PartialEq)]
+pub enum MenuEvent {
+    Quit,
+    Open(String), // a file dropped on the window
+    Close,        // escape, leave the menu
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Select,
+    Back,
+    NextTab,
+    Favorite,
 }
 
 impl InputDriver {
-    pub fn new(sdl_context: &sdl2::Sdl) -> Result<InputDriver, Box<dyn error::Error>> {
+    pub fn new(
+        sdl_context: &sdl2::Sdl,
+        keymap: &Keymap,
+        key_hints: &[(String, usize)],
+    ) -> Result<InputDriver, Box<dyn error::Error>> {
         let event_pump = sdl_context.event_pump()?;
-        Ok(InputDriver {
+
+        // controllers are optional, keyboard input still works without them
+        let controller_subsystem = sdl_context
+            .game_controller()
+            .map_err(|e| eprintln!("game controllers unavailable: {}", e))
+            .ok();
+
+        let mut input_driver = InputDriver {
             event_pump: event_pump,
-        })
+            controller_subsystem,
+            controllers: Vec::new(),
+            keymap: [Keycode::X; 16],
+            key_hints: Vec::new(),
+            fast_forward: false,
+        };
+        input_driver.set_keymap(keymap, key_hints)?;
+
+        Ok(input_driver)
+    }
+
+    // the keymap is left as it was if a key name is unknown
+    pub fn set_keymap(
+        &mut self,
+        keymap: &Keymap,
+        key_hints: &[(String, usize)],
+    ) -> Result<(), Box<dyn error::Error>> {
+        (self.keymap, self.key_hints) = keycodes(keymap, key_hints)?;
+        Ok(())
+    }
+
+    // whether set_keymap would accept a keymap, without changing anything
+    pub fn check_keymap(
+        keymap: &Keymap,
+        key_hints: &[(String, usize)],
+    ) -> Result<(), Box<dyn error::Error>> {
+        keycodes(keymap, key_hints).map(|_| ())
     }
 
-    pub fn poll_events(&mut self) -> [bool; 16] {
+    // the state of the chip8 keypad, and the emulator events since the last call
+    pub fn poll_events(&mut self) -> ([bool; 16], Vec<EmulatorEvent>) {
         let mut key_state = [false; 16];
+        let mut events = Vec::new();
+        let polled: Vec<Event> = self.event_pump.poll_iter().collect();
 
-        for event in self.event_pump.poll_iter() {
-            if let Event::Quit { .. } = event {
-                panic!("exit program") // todo
-            };
+        for event in polled {
+            match event {
+                Event::Quit { .. } => events.push(EmulatorEvent::Quit),
+                Event::DropFile { filename, .. } => events.push(EmulatorEvent::Open(filename)),
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } => {
+                    // keys mapped to the chip8 keypad are left to the program
+                    if self.key_from_keycode(keycode).is_some() {
+                        continue;
+                    }
+                    if let Some(event) = event_from_keycode(keycode) {
+                        events.push(event);
+                    }
+                }
+                Event::ControllerButtonDown {
+                    button: Button::Back,
+                    ..
+                } => events.push(EmulatorEvent::Back),
+                Event::ControllerButtonDown {
+                    button: Button::Start,
+                    ..
+                } => events.push(EmulatorEvent::Menu),
+                event => self.update_controllers(&event),
+            }
         }
 
         let keys: Vec<Keycode> = self
@@ -31,6 +144,8 @@ impl InputDriver {
             .filter_map(Keycode::from_scancode)
             .collect();
 
+        self.fast_forward = keys.contains(&Keycode::Tab);
+
         for key in keys {
             let index = self.key_from_keycode(key);
             if let Some(i) = index {
@@ -38,28 +153,147 @@ impl InputDriver {
             }
         }
 
-        key_state
+        (key_state, events)
     }
 
-    fn key_from_keycode(&self, keycode: Keycode) -> Option<usize> {
-        match keycode {
-            Keycode::Num1 => Some(0x1),
-            Keycode::Num2 => Some(0x2),
-            Keycode::Num3 => Some(0x3),
-            Keycode::Num4 => Some(0xc),
-            Keycode::Q => Some(0x4),
-            Keycode::W => Some(0x5),
-            Keycode::E => Some(0x6),
-            Keycode::R => Some(0xd),
-            Keycode::A => Some(0x7),
-            Keycode::S => Some(0x8),
-            Keycode::D => Some(0x9),
-            Keycode::F => Some(0xe),
-            Keycode::Z => Some(0xa),
-            Keycode::X => Some(0x0),
-            Keycode::C => Some(0xb),
-            Keycode::V => Some(0xf),
-            _ => None,
+    // the launcher's navigation since the last call. held keys repeat
+    pub fn poll_menu(&mut self) -> Vec<MenuEvent> {
+        let mut events = Vec::new();
+        let polled: Vec<Event> = self.event_pump.poll_iter().collect();
+
+        for event in polled {
+            let menu_event = match event {
+                Event::Quit { .. } => Some(MenuEvent::Quit),
+                Event::DropFile { filename, .. } => Some(MenuEvent::Open(filename)),
+                // escape held from leaving a program should not also quit
+                Event::KeyDown {
+                    keycode: Some(Keycode::Escape),
+                    repeat: true,
+                    ..
+                } => None,
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    ..
+                } => menu_event_from_keycode(keycode),
+                Event::ControllerButtonDown { button, .. } => menu_event_from_button(button),
+                event => {
+                    self.update_controllers(&event);
+                    None
+                }
+            };
+
+            if let Some(menu_event) = menu_event {
+                events.push(menu_event);
+            }
+        }
+
+        events
+    }
+
+    // open controllers as they are plugged in, and close them when removed
+    fn update_controllers(&mut self, event: &Event) {
+        match *event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                if let Some(subsystem) = &self.controller_subsystem {
+                    match subsystem.open(which) {
+                        Ok(controller) => self.controllers.push(controller),
+                        Err(e) => eprintln!("could not open game controller: {}", e),
+                    }
+                }
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.controllers.retain(|c| c.instance_id() != which);
+            }
+            _ => {}
         }
     }
+
+    // fast forward lasts for as long as its key is held
+    pub fn fast_forward(&self) -> bool {
+        self.fast_forward
+    }
+
+    fn key_from_keycode(&self, keycode: Keycode) -> Option<usize> {
+        self.keymap.iter().position(|&k| k == keycode).or_else(|| {
+            self.key_hints
+                .iter()
+                .find(|(k, _)| *k == keycode)
+                .map(|(_, key)| *key)
+        })
+    }
+}
+
+fn event_from_keycode(keycode: Keycode) -> Option<EmulatorEvent> {
+    match keycode {
+        Keycode::Escape => Some(EmulatorEvent::Back),
+        Keycode::F2 => Some(EmulatorEvent::Reset),
+        Keycode::F5 => Some(EmulatorEvent::Reload),
+        Keycode::F3 => Some(EmulatorEvent::SaveSettings),
+        Keycode::M => Some(EmulatorEvent::Mute),
+        Keycode::F9 => Some(EmulatorEvent::RecordAudio),
+        Keycode::F10 => Some(EmulatorEvent::RecordVideo),
+        Keycode::F12 => Some(EmulatorEvent::Screenshot),
+        Keycode::P => Some(EmulatorEvent::Pause),
+        Keycode::Equals | Keycode::KpPlus => Some(EmulatorEvent::SpeedUp),
+        Keycode::Minus | Keycode::KpMinus => Some(EmulatorEvent::SpeedDown),
+        Keycode::N => Some(EmulatorEvent::FrameAdvance),
+        Keycode::Period => Some(EmulatorEvent::InstructionStep),
+        Keycode::F1 => Some(EmulatorEvent::Menu),
+        Keycode::F8 => Some(EmulatorEvent::Stats),
+        Keycode::F6 => Some(EmulatorEvent::SaveState),
+        Keycode::F7 => Some(EmulatorEvent::LoadState),
+        _ => None,
+    }
+}
+
+fn menu_event_from_keycode(keycode: Keycode) -> Option<MenuEvent> {
+    match keycode {
+        Keycode::Up => Some(MenuEvent::Up),
+        Keycode::Down => Some(MenuEvent::Down),
+        Keycode::PageUp => Some(MenuEvent::PageUp),
+        Keycode::PageDown => Some(MenuEvent::PageDown),
+        Keycode::Left => Some(MenuEvent::Left),
+        Keycode::Right => Some(MenuEvent::Right),
+        Keycode::Return | Keycode::KpEnter => Some(MenuEvent::Select),
+        Keycode::Backspace => Some(MenuEvent::Back),
+        Keycode::Tab => Some(MenuEvent::NextTab),
+        Keycode::F => Some(MenuEvent::Favorite),
+        Keycode::Escape => Some(MenuEvent::Close),
+        _ => None,
+    }
+}
+
+fn menu_event_from_button(button: Button) -> Option<MenuEvent> {
+    match button {
+        Button::DPadUp => Some(MenuEvent::Up),
+        Button::DPadDown => Some(MenuEvent::Down),
+        Button::DPadLeft => Some(MenuEvent::Left),
+        Button::DPadRight => Some(MenuEvent::Right),
+        Button::A | Button::Start => Some(MenuEvent::Select),
+        Button::B => Some(MenuEvent::Back),
+        Button::X => Some(MenuEvent::Favorite),
+        Button::LeftShoulder | Button::RightShoulder => Some(MenuEvent::NextTab),
+        _ => None,
+    }
+}
+
+type Keycodes = ([Keycode; 16], Vec<(Keycode, usize)>);
+
+// the sdl keys for a keymap and for the key hints
+fn keycodes(
+    keymap: &Keymap,
+    key_hints: &[(String, usize)],
+) -> Result<Keycodes, Box<dyn error::Error>> {
+    let mut keycodes = [Keycode::X; 16];
+    for (keycode, name) in keycodes.iter_mut().zip(keymap.keys.iter()) {
+        *keycode = Keycode::from_name(name).ok_or(format!("unknown key '{}'", name))?;
+    }
+
+    let mut hints = Vec::new();
+    for (name, key) in key_hints {
+        let keycode = Keycode::from_name(name).ok_or(format!("unknown key '{}'", name))?;
+        hints.push((keycode, *key));
+    }
+
+    Ok((keycodes, hints))
 }
@@ -1,12 +1,23 @@
 mod audio_driver;
+mod buzzer;
+#[cfg(test)]
+mod buzzer_tests;
 mod display_driver;
+mod font;
 mod input_driver;
 
+pub use self::audio_driver::load_beep_sample;
 pub use self::audio_driver::AudioDriver;
+pub use self::buzzer::AudioSettings;
+pub use self::buzzer::BeepSound;
+pub use self::buzzer::Buzzer;
+pub use self::buzzer::Waveform;
+pub use self::buzzer::SAMPLE_RATE;
 pub use self::display_driver::DisplayDriver;
+pub use self::input_driver::EmulatorEvent;
 pub use self::input_driver::InputDriver;
+pub use self::input_driver::MenuEvent;
 
-pub const BACKGROUND_COLOR: (u8, u8, u8) = (0, 0, 0);
-pub const PIXEL_COLOR: (u8, u8, u8) = (173, 140, 255);
+pub const WINDOW_SCALE: u32 = 20;
 pub const CHIP8_WIDTH: u32 = 64;
 pub const CHIP8_HEIGHT: u32 = 32;
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
+
+// keyboard key names for each of the 16 chip8 keys, using sdl key names
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "Vec<String>", into = "Vec<String>")]
+pub struct Keymap {
+    pub keys: [String; 16],
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        // 1 2 3 C        1 2 3 4
+        // 4 5 6 D   <-   Q W E R
+        // 7 8 9 E        A S D F
+        // A 0 B F        Z X C V
+        let keys = [
+            "X", "1", "2", "3", "Q", "W", "E", "A", "S", "D", "Z", "C", "4", "R", "F", "V",
+        ];
+
+        Keymap {
+            keys: keys.map(String::from),
+        }
+    }
+}
+
+impl Keymap {
+    pub const LAYOUTS: [&'static str; 2] = ["default", "numpad"];
+
+    pub fn layout(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Keymap::default()),
+            "numpad" => {
+                // 1 2 3 C        7 8 9 /
+                // 4 5 6 D   <-   4 5 6 *
+                // 7 8 9 E        1 2 3 -
+                // A 0 B F        0 . Enter +
+                let keys = [
+                    "Keypad .",
+                    "Keypad 7",
+                    "Keypad 8",
+                    "Keypad 9",
+                    "Keypad 4",
+                    "Keypad 5",
+                    "Keypad 6",
+                    "Keypad 1",
+                    "Keypad 2",
+                    "Keypad 3",
+                    "Keypad 0",
+                    "Keypad Enter",
+                    "Keypad /",
+                    "Keypad *",
+                    "Keypad -",
+                    "Keypad +",
+                ];
+                Some(Keymap {
+                    keys: keys.map(String::from),
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+impl TryFrom<Vec<String>> for Keymap {
+    type Error = String;
+
+    fn try_from(names: Vec<String>) -> Result<Self, Self::Error> {
+        if names.len() != 16 {
+            return Err(format!("keymap needs 16 keys, found {}", names.len()));
+        }
+
+        let mut keymap = Keymap::default();
+        keymap.keys.clone_from_slice(&names);
+        Ok(keymap)
+    }
+}
+
+impl From<Keymap> for Vec<String> {
+    fn from(keymap: Keymap) -> Self {
+        keymap.keys.to_vec()
+    }
+}
@@ -0,0 +1,497 @@
+use crate::config::Config;
+use crate::database::Database;
+use crate::emulator;
+use crate::emulator::{Drivers, Exit};
+use crate::io::DisplayDriver;
+use crate::io::MenuEvent;
+use crate::load_rom;
+use crate::settings;
+use crate::FRAME_RATE;
+use serde::{Deserialize, Serialize};
+use spin_sleep::LoopHelper;
+use std::collections::HashMap;
+use std::env;
+use std::error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const EXTENSIONS: [&str; 3] = ["ch8", "sc8", "xo8"];
+const MAX_RECENT: usize = 20;
+const PAGE_SIZE: usize = 10;
+const HINTS: &str = "enter play  f favorite  tab next list  esc quit";
+
+// what the launcher remembers between runs
+#[derive(Default, Deserialize, Serialize)]
+#[serde(default)]
+pub(super) struct LauncherState {
+    last_dir: Option<PathBuf>,
+    pub(super) recent: Vec<PathBuf>, // most recent first
+    pub(super) favorites: Vec<PathBuf>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Files,
+    Recent,
+    Favorites,
+}
+
+const TABS: [Tab; 3] = [Tab::Files, Tab::Recent, Tab::Favorites];
+
+struct Entry {
+    path: PathBuf,
+    label: String,
+    is_dir: bool,
+}
+
+struct Launcher {
+    state: LauncherState,
+    state_path: Option<PathBuf>,
+    database: Option<Arc<Database>>,
+    titles: HashMap<PathBuf, Option<String>>, // database titles, read once per file
+    tab: Tab,
+    dir: PathBuf,
+    entries: Vec<Entry>,
+    selected: usize,
+    scroll: usize, // first entry shown
+}
+
+// browse for a program and run it, returning to the list when escape is
+// pressed, until the launcher itself is closed
+pub fn run(drivers: &mut Drivers, cli_config: &Config) -> Result<(), Box<dyn error::Error>> {
+    let mut launcher = Launcher::new(settings::load_database(cli_config)?);
+    let mut loop_helper = LoopHelper::builder().build_with_target_rate(FRAME_RATE);
+
+    drivers.display.set_title("chip8")?;
+
+    loop {
+        loop_helper.loop_start();
+
+        let mut launch = None;
+        for event in drivers.input.poll_menu() {
+            match event {
+                MenuEvent::Quit | MenuEvent::Close => {
+                    launcher.save();
+                    return Ok(());
+                }
+                MenuEvent::Open(filename) => launch = Some(PathBuf::from(filename)),
+                event => launch = launcher.handle(event).or(launch),
+            }
+        }
+
+        if let Some(path) = launch {
+            if launcher.play(drivers, cli_config, &path)? == Exit::Quit {
+                launcher.save();
+                return Ok(());
+            }
+
+            // programs can change the palette and title
+            drivers.display.set_palette(&cli_config.palette);
+            drivers.display.set_title("chip8")?;
+            launcher.refresh();
+        }
+
+        launcher.draw(&mut drivers.display)?;
+        loop_helper.loop_sleep();
+    }
+}
+
+impl Launcher {
+    fn new(database: Option<Arc<Database>>) -> Self {
+        let state_path = LauncherState::default_path();
+        let state = match &state_path {
+            Some(path) => LauncherState::load(path).unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                LauncherState::default()
+            }),
+            None => LauncherState::default(),
+        };
+
+        let dir = state
+            .last_dir
+            .clone()
+            .filter(|dir| dir.is_dir())
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        let mut launcher = Launcher {
+            state,
+            state_path,
+            database,
+            titles: HashMap::new(),
+            tab: Tab::Files,
+            dir,
+            entries: Vec::new(),
+            selected: 0,
+            scroll: 0,
+        };
+        launcher.refresh();
+        launcher
+    }
+
+    // the program to run, if one was chosen
+    fn handle(&mut self, event: MenuEvent) -> Option<PathBuf> {
+        let count = self.entries.len();
+
+        let event = match event {
+            MenuEvent::Left => MenuEvent::Back,
+            MenuEvent::Right => MenuEvent::Select,
+            event => event,
+        };
+
+        match event {
+            MenuEvent::Up if count > 0 => self.selected = (self.selected + count - 1) % count,
+            MenuEvent::Down if count > 0 => self.selected = (self.selected + 1) % count,
+            MenuEvent::PageUp => self.selected = self.selected.saturating_sub(PAGE_SIZE),
+            MenuEvent::PageDown => {
+                self.selected = (self.selected + PAGE_SIZE).min(count.saturating_sub(1))
+            }
+            MenuEvent::NextTab => {
+                let index = TABS.iter().position(|&t| t == self.tab).unwrap_or(0);
+                self.tab = TABS[(index + 1) % TABS.len()];
+                self.selected = 0;
+                self.refresh();
+            }
+            MenuEvent::Back => match self.tab {
+                Tab::Files => self.open_parent(),
+                _ => {
+                    self.tab = Tab::Files;
+                    self.selected = 0;
+                    self.refresh();
+                }
+            },
+            MenuEvent::Favorite => {
+                if let Some(entry) = self.entries.get(self.selected).filter(|e| !e.is_dir) {
+                    let path = entry.path.clone();
+                    self.state.toggle_favorite(&path);
+                    self.save();
+                    self.refresh();
+                }
+            }
+            MenuEvent::Select => {
+                let entry = self.entries.get(self.selected)?;
+                if !entry.is_dir {
+                    return Some(entry.path.clone());
+                }
+
+                self.dir = entry.path.clone();
+                self.selected = 0;
+                self.refresh();
+            }
+            _ => {}
+        }
+
+        None
+    }
+
+    // go up a directory, keeping the one we came from selected
+    fn open_parent(&mut self) {
+        let Some(parent) = self.dir.parent().map(Path::to_path_buf) else {
+            return;
+        };
+
+        let previous = std::mem::replace(&mut self.dir, parent);
+        self.refresh();
+        self.selected = self
+            .entries
+            .iter()
+            .position(|e| e.path == previous)
+            .unwrap_or(0);
+    }
+
+    fn play(
+        &mut self,
+        drivers: &mut Drivers,
+        cli_config: &Config,
+        path: &Path,
+    ) -> Result<Exit, Box<dyn error::Error>> {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let filename = path.to_string_lossy();
+
+        let (config, program) = match load_rom(cli_config, &filename) {
+            Ok(x) => x,
+            Err(e) => {
+                eprintln!("{}", e);
+                drivers.display.show_error(&e.to_string());
+                return Ok(Exit::Back);
+            }
+        };
+
+        self.state.add_recent(&path);
+        self.save();
+
+        // a program that fails to start should not take the launcher with it
+        emulator::run(drivers, cli_config, config, program, true).or_else(|e| {
+            eprintln!("{}", e);
+            drivers.display.show_error(&e.to_string());
+            Ok(Exit::Back)
+        })
+    }
+
+    fn save(&mut self) {
+        if self.tab == Tab::Files {
+            self.state.last_dir = Some(self.dir.clone());
+        }
+
+        if let Some(path) = &self.state_path {
+            if let Err(e) = self.state.save(path) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    // list the current tab again
+    fn refresh(&mut self) {
+        let paths = match self.tab {
+            Tab::Files => {
+                self.entries = list_dir(&self.dir);
+                Vec::new()
+            }
+            Tab::Recent => self.state.recent.clone(),
+            Tab::Favorites => self.state.favorites.clone(),
+        };
+
+        if self.tab != Tab::Files {
+            self.entries = paths
+                .into_iter()
+                .map(|path| Entry {
+                    label: file_name(&path),
+                    path,
+                    is_dir: false,
+                })
+                .collect();
+        }
+
+        for i in 0..self.entries.len() {
+            if self.entries[i].is_dir {
+                continue;
+            }
+            let path = self.entries[i].path.clone();
+            if let Some(title) = self.title(&path) {
+                let entry = &mut self.entries[i];
+                entry.label = format!("{} ({})", title, entry.label);
+            }
+        }
+
+        self.selected = self.selected.min(self.entries.len().saturating_sub(1));
+    }
+
+    fn title(&mut self, path: &Path) -> Option<String> {
+        let database = self.database.as_ref()?;
+
+        self.titles
+            .entry(path.to_path_buf())
+            .or_insert_with(|| {
+                let program = fs::read(path).ok()?;
+                database
+                    .lookup(&settings::rom_hash(&program))
+                    .map(|info| info.title)
+            })
+            .clone()
+    }
+
+    fn draw(&mut self, display: &mut DisplayDriver) -> Result<(), Box<dyn error::Error>> {
+        let (width, height) = display.size();
+        let palette = *display.palette();
+        let text_scale = display.text_scale() as i32;
+        let line = display.line_height() as i32;
+        let char_width = display.char_width() as i32;
+        let margin = text_scale * 4;
+        let columns = ((width as i32 - 2 * margin) / char_width).max(1) as usize;
+
+        display.clear();
+
+        // tabs, the current one inverted
+        let mut x = margin;
+        for tab in TABS {
+            let name = tab.name();
+            let name_width = name.len() as i32 * char_width;
+            if tab == self.tab {
+                display.fill_rect(
+                    x - text_scale * 2,
+                    margin - text_scale * 2,
+                    (name_width + text_scale * 3) as u32,
+                    line as u32,
+                    palette.pixel,
+                    255,
+                )?;
+                display.draw_text(x, margin, name, palette.background)?;
+            } else {
+                display.draw_text(x, margin, name, palette.pixel)?;
+            }
+            x += name_width + 2 * char_width;
+        }
+
+        let location = match self.tab {
+            Tab::Files => self.dir.to_string_lossy().to_string(),
+            Tab::Recent => "recently played".to_string(),
+            Tab::Favorites => "favorites".to_string(),
+        };
+        display.draw_text(
+            margin,
+            margin + line,
+            &fit_left(&location, columns),
+            palette.pixel,
+        )?;
+
+        let top = margin + line * 5 / 2;
+        let bottom = height as i32 - margin - line;
+        let rows = ((bottom - top) / line).max(1) as usize;
+
+        // keep the selection in view
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+
+        if self.entries.is_empty() {
+            display.draw_text(margin, top, "no programs here", palette.pixel)?;
+        }
+
+        for (i, entry) in self.entries.iter().enumerate().skip(self.scroll).take(rows) {
+            let y = top + (i - self.scroll) as i32 * line;
+            if i == self.selected {
+                display.fill_rect(0, y - text_scale * 2, width, line as u32, palette.pixel, 64)?;
+            }
+
+            let marker = if self.state.favorites.contains(&entry.path) {
+                '*'
+            } else {
+                ' '
+            };
+            let suffix = if entry.is_dir { "/" } else { "" };
+            let text = format!("{}{}{}", marker, entry.label, suffix);
+            display.draw_text(margin, y, &fit_right(&text, columns), palette.pixel)?;
+        }
+
+        display.draw_text(margin, bottom, &fit_right(HINTS, columns), palette.pixel)?;
+        display.present();
+
+        Ok(())
+    }
+}
+
+impl Tab {
+    fn name(self) -> &'static str {
+        match self {
+            Tab::Files => "Files",
+            Tab::Recent => "Recent",
+            Tab::Favorites => "Favorites",
+        }
+    }
+}
+
+impl LauncherState {
+    // $XDG_DATA_HOME/chip8/launcher.toml on linux
+    fn default_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("chip8").join("launcher.toml"))
+    }
+
+    // a missing file is an empty state
+    fn load(path: &Path) -> Result<Self, Box<dyn error::Error>> {
+        if !path.exists() {
+            return Ok(LauncherState::default());
+        }
+
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read '{}': {}", path.display(), e))?;
+        toml::from_str(&text)
+            .map_err(|e| format!("could not read '{}': {}", path.display(), e).into())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), Box<dyn error::Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, toml::to_string(self)?)
+            .map_err(|e| format!("could not write '{}': {}", path.display(), e).into())
+    }
+
+    pub(super) fn add_recent(&mut self, path: &Path) {
+        self.recent.retain(|p| p != path);
+        self.recent.insert(0, path.to_path_buf());
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    pub(super) fn toggle_favorite(&mut self, path: &Path) {
+        if self.favorites.iter().any(|p| p == path) {
+            self.favorites.retain(|p| p != path);
+        } else {
+            self.favorites.push(path.to_path_buf());
+        }
+    }
+}
+
+// the parent directory, then directories and programs sorted by name. hidden
+// files and unreadable directories are left out
+fn list_dir(dir: &Path) -> Vec<Entry> {
+    let mut dirs = Vec::new();
+    let mut programs = Vec::new();
+
+    for entry in fs::read_dir(dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        let label = file_name(&path);
+        if label.starts_with('.') {
+            continue;
+        }
+
+        if path.is_dir() {
+            dirs.push(Entry {
+                path,
+                label,
+                is_dir: true,
+            });
+        } else if is_program(&path) {
+            programs.push(Entry {
+                path,
+                label,
+                is_dir: false,
+            });
+        }
+    }
+
+    dirs.sort_by_key(|e| e.label.to_lowercase());
+    programs.sort_by_key(|e| e.label.to_lowercase());
+
+    let parent = dir.parent().map(|parent| Entry {
+        path: parent.to_path_buf(),
+        label: "..".to_string(),
+        is_dir: true,
+    });
+
+    parent.into_iter().chain(dirs).chain(programs).collect()
+}
+
+fn is_program(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .is_some_and(|e| EXTENSIONS.contains(&e.to_lowercase().as_str()))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map_or_else(|| path.to_string_lossy(), |name| name.to_string_lossy())
+        .to_string()
+}
+
+// cut text to fit, keeping the end, for paths
+pub(super) fn fit_left(text: &str, columns: usize) -> String {
+    let count = text.chars().count();
+    if count <= columns {
+        return text.to_string();
+    }
+    let skip = count - columns + 3;
+    format!("...{}", text.chars().skip(skip).collect::<String>())
+}
+
+// cut text to fit, keeping the start
+pub(super) fn fit_right(text: &str, columns: usize) -> String {
+    if text.chars().count() <= columns {
+        return text.to_string();
+    }
+    let keep = columns.saturating_sub(3);
+    format!("{}...", text.chars().take(keep).collect::<String>())
+}
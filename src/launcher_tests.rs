@@ -0,0 +1,55 @@
+use crate::launcher::{fit_left, fit_right, LauncherState};
+use std::path::{Path, PathBuf};
+
+#[test]
+fn test_fit_left() {
+    assert_eq!(fit_left("roms/pong.ch8", 20), "roms/pong.ch8");
+    assert_eq!(fit_left("roms/pong.ch8", 13), "roms/pong.ch8");
+    // the end of a path is the part that tells files apart
+    assert_eq!(fit_left("roms/pong.ch8", 10), "...ong.ch8");
+    assert_eq!(fit_left("ромы/понг.ch8", 10), "...онг.ch8");
+}
+
+#[test]
+fn test_fit_right() {
+    assert_eq!(fit_right("Space Invaders", 20), "Space Invaders");
+    assert_eq!(fit_right("Space Invaders", 14), "Space Invaders");
+    assert_eq!(fit_right("Space Invaders", 10), "Space I...");
+    assert_eq!(fit_right("Космические", 8), "Косми...");
+    assert_eq!(fit_right("Space Invaders", 2), "...");
+}
+
+#[test]
+fn test_add_recent() {
+    let mut state = LauncherState::default();
+    state.add_recent(Path::new("a.ch8"));
+    state.add_recent(Path::new("b.ch8"));
+    state.add_recent(Path::new("a.ch8"));
+
+    // most recent first, each program once
+    assert_eq!(
+        state.recent,
+        [PathBuf::from("a.ch8"), PathBuf::from("b.ch8")]
+    );
+
+    for i in 0..30 {
+        state.add_recent(Path::new(&format!("{}.ch8", i)));
+    }
+    assert_eq!(state.recent.len(), 20);
+    assert_eq!(state.recent[0], PathBuf::from("29.ch8"));
+    assert_eq!(state.recent[19], PathBuf::from("10.ch8"));
+}
+
+#[test]
+fn test_toggle_favorite() {
+    let mut state = LauncherState::default();
+    state.toggle_favorite(Path::new("a.ch8"));
+    state.toggle_favorite(Path::new("b.ch8"));
+    assert_eq!(
+        state.favorites,
+        [PathBuf::from("a.ch8"), PathBuf::from("b.ch8")]
+    );
+
+    state.toggle_favorite(Path::new("a.ch8"));
+    assert_eq!(state.favorites, [PathBuf::from("b.ch8")]);
+}
@@ -0,0 +1,5 @@
+// the emulator core, shared by the chip8 binary, the rom tests in tests/ and
+// recompiled programs
+pub mod host;
+pub mod processor;
+pub mod testing;
@@ -1,98 +1,233 @@
+mod bench;
+mod compare;
+#[cfg(test)]
+mod compare_tests;
+mod config;
+mod database;
+#[cfg(test)]
+mod database_tests;
+mod emulator;
+mod file_watcher;
+mod headless;
+#[cfg(test)]
+mod headless_tests;
+mod input_movie;
+#[cfg(test)]
+mod input_movie_tests;
 mod io;
-mod processor;
-
-use crate::io::AudioDriver;
-use crate::io::DisplayDriver;
-use crate::io::InputDriver;
-use crate::processor::Cpu;
-use spin_sleep::LoopHelper;
+mod keymap;
+mod launcher;
+#[cfg(test)]
+mod launcher_tests;
+mod overlay;
+mod palette;
+mod recording;
+mod settings;
+#[cfg(test)]
+mod settings_tests;
+
+use crate::config::Command;
+use crate::config::Config;
+use crate::emulator::Drivers;
+use crate::processor::{Cpu, Symbols, TraceFile};
+use chip8::processor;
 use std::env;
 use std::error;
 use std::fs;
-use std::path;
+use std::path::Path;
+use std::process;
 
 const TARGET_FREQUENCY: u32 = 600;
+const FRAME_RATE: u32 = 60;
+const INSTRUCTIONS_PER_FRAME: u32 = TARGET_FREQUENCY / FRAME_RATE;
+const MAX_INSTRUCTIONS_PER_FRAME: u32 = 1000;
+const VIDEO_SCALE: u32 = 10;
 
 fn main() {
     let args: Vec<String> = env::args().collect();
     let called_program = &args[0];
 
-    if args.len() != 2 {
-        print_usage_string(called_program);
-        return;
-    }
-
-    let config = match Config::new(&args) {
-        Ok(x) => x,
-        Err(e) => {
-            eprintln!("{}", e);
+    let cli_config = match config::parse_args(&args[1..]) {
+        Ok(Command::Run(config)) => *config,
+        Ok(Command::Launch(config)) => {
+            let result =
+                Drivers::new(&config).and_then(|mut drivers| launcher::run(&mut drivers, &config));
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
             return;
         }
+        Ok(Command::Bench(config)) => {
+            let result = load_rom(&config, &config.filename)
+                .and_then(|(config, program)| bench::run(&config, &program));
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            return;
+        }
+        Ok(Command::Recompile(config)) => {
+            let result = load_rom(&config, &config.filename)
+                .and_then(|(config, program)| write_recompiled(&config, &program));
+            if let Err(e) = result {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+            return;
+        }
+        Ok(Command::Help) => {
+            println!("{}", config::USAGE);
+            return;
+        }
+        Ok(Command::Version) => {
+            println!("chip8 {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            eprintln!("Run '{} --help' for usage", called_program);
+            process::exit(2);
+        }
     };
 
-    let program = match read_program(&config.filename) {
+    let (config, program) = match load_rom(&cli_config, &cli_config.filename) {
         Ok(x) => x,
         Err(e) => {
             eprintln!("{}", e);
-            return;
+            process::exit(1);
         }
     };
 
-    let sdl_context = sdl2::init().unwrap();
-    let mut display_driver = DisplayDriver::new(&sdl_context).unwrap();
-    let audio_driver = AudioDriver::new(&sdl_context).unwrap();
-    let mut input_driver = InputDriver::new(&sdl_context).unwrap();
-    let mut cpu = Cpu::new(&program).unwrap();
-
-    let mut loop_helper = LoopHelper::builder().build_with_target_rate(TARGET_FREQUENCY);
-
-    loop {
-        loop_helper.loop_start();
+    // differences are reported by compare, the exit code tells scripts about them
+    if let Some(reference) = &config.compare {
+        match compare::run(&config, &program, reference) {
+            Ok(true) => return,
+            Ok(false) => process::exit(1),
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(2);
+            }
+        }
+    }
 
-        let key_state = input_driver.poll_events();
+    let result = if config.headless {
+        headless::run(&config, &program)
+    } else {
+        Drivers::new(&config)
+            .and_then(|mut drivers| {
+                emulator::run(&mut drivers, &cli_config, config, program, false)
+            })
+            .map(|_| ())
+    };
 
-        let cycle_output = match cpu.cycle(&key_state) {
-            Ok(p) => p,
-            Err(e) => panic!("{}", e),
-        };
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
 
-        if cycle_output.redraw_flag {
-            display_driver.render(&cpu.display).unwrap();
-        }
+// read a program and work out its settings from the command line, the config
+// file and the database
+pub fn load_rom(
+    cli_config: &Config,
+    filename: &str,
+) -> Result<(Config, Vec<u8>), Box<dyn error::Error>> {
+    let program =
+        read_program(filename).map_err(|e| format!("could not read '{}': {}", filename, e))?;
+
+    let mut config = cli_config.clone();
+    config.filename = filename.to_string();
+    settings::apply_settings(&mut config, &program)?;
+    Ok((config, program))
+}
 
-        if cycle_output.sound_flag {
-            audio_driver.start_beep();
-        } else {
-            audio_driver.stop_beep();
+// translate a program to rust source, see processor::recompile
+fn write_recompiled(config: &Config, program: &[u8]) -> Result<(), Box<dyn error::Error>> {
+    let name = Path::new(&config.filename)
+        .file_name()
+        .map_or(config.filename.clone(), |n| n.to_string_lossy().to_string());
+    let source = processor::recompile(program, &config.cpu, config.instructions_per_frame, &name);
+
+    match &config.output {
+        Some(filename) => {
+            fs::write(filename, source)
+                .map_err(|e| format!("could not write '{}': {}", filename, e))?;
+            println!("saved '{}'", filename);
         }
-
-        loop_helper.loop_sleep();
+        None => print!("{}", source),
     }
+    Ok(())
 }
 
-struct Config {
-    filename: String,
+// a program file that can be run, an empty one is usually still being written
+pub fn read_program(filename: &str) -> Result<Vec<u8>, Box<dyn error::Error>> {
+    let program = fs::read(filename)?;
+    if program.is_empty() {
+        return Err("the file is empty".into());
+    }
+    Ok(program)
 }
 
-impl Config {
-    pub fn new(args: &[String]) -> Result<Config, Box<dyn error::Error>> {
-        let filename = &args[1];
+// write the profile, coverage and heatmap that were asked for
+pub fn save_reports(
+    config: &Config,
+    cpu: &Cpu,
+    program_length: usize,
+) -> Result<(), Box<dyn error::Error>> {
+    // a broken symbol file only matters for the reports that use it
+    let symbols = if config.profile.is_some() || config.coverage.is_some() {
+        load_symbols(config)?
+    } else {
+        Symbols::default()
+    };
+
+    if let (Some(filename), Some(profiler)) = (&config.profile, &cpu.profiler) {
+        profiler.write_report(filename, cpu.memory(), &symbols)?;
+        println!("saved profile '{}'", filename);
+    }
 
-        if !path::Path::new(filename).exists() {
-            return Err(format!("file '{}' does not exist", filename).into());
-        }
+    if let (Some(filename), Some(coverage)) = (&config.coverage, &cpu.coverage) {
+        let program = (config.cpu.start_address, program_length);
+        coverage.write_lcov(filename, cpu.memory(), program, &symbols)?;
+        println!("saved coverage '{}'", filename);
+    }
 
-        Ok(Config {
-            filename: filename.clone(),
-        })
+    if let (Some(filename), Some(coverage)) = (&config.heatmap, &cpu.coverage) {
+        coverage.write_heatmap(filename)?;
+        println!("saved heatmap '{}'", filename);
     }
+
+    Ok(())
 }
 
-fn print_usage_string(called_program: &str) {
-    eprintln!("Usage: {} <program>", called_program);
+// called when the program faults, before the fault is returned. the trace and
+// reports up to the fault are the part that explains it, so they are written
+// out as on a clean exit
+pub fn save_before_fault(
+    config: &Config,
+    cpu: &mut Cpu,
+    trace: Option<TraceFile>,
+    program_length: usize,
+) -> Result<(), Box<dyn error::Error>> {
+    if let Some(mut trace) = trace {
+        trace.write(cpu)?;
+        trace.finish()?;
+    }
+    save_reports(config, cpu, program_length)
 }
 
-fn read_program(filename: &str) -> Result<Vec<u8>, Box<dyn error::Error>> {
-    let program = fs::read(filename)?;
-    Ok(program)
+// the --symbols file, or a .sym file next to the program
+pub fn load_symbols(config: &Config) -> Result<Symbols, Box<dyn error::Error>> {
+    match &config.symbols {
+        Some(filename) => Symbols::load(Path::new(filename)),
+        None => {
+            let path = Path::new(&config.filename).with_extension("sym");
+            if path.exists() {
+                Symbols::load(&path)
+            } else {
+                Ok(Symbols::default())
+            }
+        }
+    }
 }
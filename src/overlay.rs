@@ -0,0 +1,352 @@
+use crate::config::Config;
+use crate::io::DisplayDriver;
+use crate::io::EmulatorEvent;
+use crate::io::MenuEvent;
+use crate::keymap::Keymap;
+use crate::palette::Palette;
+use crate::processor::Quirks;
+use crate::FRAME_RATE;
+use std::error;
+use std::time::{Duration, Instant};
+
+const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+const MAX_MESSAGES: usize = 4;
+
+// text drawn over the emulated display: short messages about what just
+// happened, the frame rate and the pause menu
+pub struct Overlay {
+    messages: Vec<(String, Instant)>, // with the time they were shown
+    show_stats: bool,
+    frames: u32, // emulated frames since the frame rate was last measured
+    measured: Instant,
+    fps: f64,
+    menu: Option<PauseMenu>,
+    visible: bool, // anything was drawn last time
+}
+
+// what the pause menu wants changed
+pub enum MenuAction {
+    Event(EmulatorEvent),
+    Palette(Palette),
+    Quirks(Quirks),
+    Keymap(Box<Keymap>),
+}
+
+struct PauseMenu {
+    selected: usize,
+    palette: Choice<Palette>,
+    quirks: Choice<Quirks>,
+    keymap: Choice<Keymap>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Item {
+    Resume,
+    SaveState,
+    LoadState,
+    Palette,
+    Speed,
+    Quirks,
+    Keymap,
+    Reset,
+    Exit,
+}
+
+const ITEMS: [Item; 9] = [
+    Item::Resume,
+    Item::SaveState,
+    Item::LoadState,
+    Item::Palette,
+    Item::Speed,
+    Item::Quirks,
+    Item::Keymap,
+    Item::Reset,
+    Item::Exit,
+];
+
+// one of a list of named values, stepped through with left and right
+struct Choice<T> {
+    options: Vec<(String, T)>,
+    index: usize,
+}
+
+impl Overlay {
+    pub fn new() -> Self {
+        Overlay {
+            messages: Vec::new(),
+            show_stats: false,
+            frames: 0,
+            measured: Instant::now(),
+            fps: 0.0,
+            menu: None,
+            visible: false,
+        }
+    }
+
+    // shown on screen for a few seconds, and printed
+    pub fn info(&mut self, text: &str) {
+        println!("{}", text);
+        self.show(text);
+    }
+
+    pub fn error(&mut self, text: &str) {
+        eprintln!("{}", text);
+        self.show(text);
+    }
+
+    fn show(&mut self, text: &str) {
+        self.messages.push((text.to_string(), Instant::now()));
+        if self.messages.len() > MAX_MESSAGES {
+            self.messages.remove(0);
+        }
+    }
+
+    pub fn toggle_stats(&mut self) {
+        self.show_stats = !self.show_stats;
+    }
+
+    pub fn count_frame(&mut self) {
+        self.frames += 1;
+    }
+
+    pub fn menu_open(&self) -> bool {
+        self.menu.is_some()
+    }
+
+    // the menu starts out showing the program's current settings
+    pub fn open_menu(&mut self, config: &Config) {
+        self.menu = Some(PauseMenu::new(config));
+    }
+
+    pub fn handle_menu(&mut self, event: MenuEvent) -> Option<MenuAction> {
+        let menu = self.menu.as_mut()?;
+        let item = ITEMS[menu.selected];
+
+        let (action, close) = match event {
+            MenuEvent::Quit => (Some(MenuAction::Event(EmulatorEvent::Quit)), true),
+            MenuEvent::Open(filename) => {
+                (Some(MenuAction::Event(EmulatorEvent::Open(filename))), true)
+            }
+            MenuEvent::Close | MenuEvent::Back => (None, true),
+            MenuEvent::Up => {
+                menu.selected = (menu.selected + ITEMS.len() - 1) % ITEMS.len();
+                (None, false)
+            }
+            MenuEvent::Down => {
+                menu.selected = (menu.selected + 1) % ITEMS.len();
+                (None, false)
+            }
+            MenuEvent::Left => (menu.change(item, false), false),
+            MenuEvent::Right => (menu.change(item, true), false),
+            MenuEvent::Select => match item {
+                Item::Resume => (None, true),
+                Item::SaveState => (Some(MenuAction::Event(EmulatorEvent::SaveState)), true),
+                Item::LoadState => (Some(MenuAction::Event(EmulatorEvent::LoadState)), true),
+                Item::Reset => (Some(MenuAction::Event(EmulatorEvent::Reset)), true),
+                Item::Exit => (Some(MenuAction::Event(EmulatorEvent::Back)), true),
+                _ => (menu.change(item, true), false),
+            },
+            _ => (None, false),
+        };
+
+        if close {
+            self.menu = None;
+        }
+        action
+    }
+
+    // drop old messages and measure the frame rate, true if the display
+    // needs drawing again for the overlay to show or disappear
+    pub fn update(&mut self) -> bool {
+        self.messages
+            .retain(|(_, shown)| shown.elapsed() < MESSAGE_DURATION);
+
+        let elapsed = self.measured.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.fps = self.frames as f64 / elapsed.as_secs_f64();
+            self.frames = 0;
+            self.measured = Instant::now();
+        }
+
+        let visible = !self.messages.is_empty() || self.show_stats || self.menu.is_some();
+        let changed = visible || self.visible;
+        self.visible = visible;
+        changed
+    }
+
+    pub fn draw(&self, display: &mut DisplayDriver, ipf: u32) -> Result<(), Box<dyn error::Error>> {
+        let (width, height) = display.size();
+        let margin = display.text_scale() as i32 * 4;
+        let line = display.line_height() as i32;
+        let char_width = display.char_width() as i32;
+
+        if self.show_stats {
+            let text = format!(
+                "{:.0} fps  x{:.2}  {} ipf",
+                self.fps,
+                self.fps / FRAME_RATE as f64,
+                ipf
+            );
+            let x = width as i32 - margin - text.len() as i32 * char_width;
+            draw_label(display, x, margin, &text)?;
+        }
+
+        for (i, (text, _)) in self.messages.iter().rev().enumerate() {
+            let y = height as i32 - margin - (i as i32 + 1) * line;
+            draw_label(display, margin, y, text)?;
+        }
+
+        if let Some(menu) = &self.menu {
+            menu.draw(display, ipf)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl PauseMenu {
+    fn new(config: &Config) -> Self {
+        let palettes = Palette::NAMES
+            .iter()
+            .filter_map(|name| Some((name.to_string(), name.parse().ok()?)))
+            .collect();
+        let quirks = Quirks::PROFILES
+            .iter()
+            .filter_map(|name| Some((name.to_string(), name.parse().ok()?)))
+            .collect();
+        let keymaps = Keymap::LAYOUTS
+            .iter()
+            .filter_map(|name| Some((name.to_string(), Keymap::layout(name)?)))
+            .collect();
+
+        PauseMenu {
+            selected: 0,
+            palette: Choice::new(&config.palette, palettes),
+            quirks: Choice::new(&config.cpu.quirks, quirks),
+            keymap: Choice::new(&config.keymap, keymaps),
+        }
+    }
+
+    fn change(&mut self, item: Item, forward: bool) -> Option<MenuAction> {
+        match item {
+            Item::Palette => Some(MenuAction::Palette(self.palette.step(forward))),
+            Item::Quirks => Some(MenuAction::Quirks(self.quirks.step(forward))),
+            Item::Keymap => Some(MenuAction::Keymap(Box::new(self.keymap.step(forward)))),
+            Item::Speed if forward => Some(MenuAction::Event(EmulatorEvent::SpeedUp)),
+            Item::Speed => Some(MenuAction::Event(EmulatorEvent::SpeedDown)),
+            _ => None,
+        }
+    }
+
+    fn label(&self, item: Item, ipf: u32) -> String {
+        match item {
+            Item::Resume => "Resume".to_string(),
+            Item::SaveState => "Save state".to_string(),
+            Item::LoadState => "Load state".to_string(),
+            Item::Palette => format!("Palette  < {} >", self.palette.name()),
+            Item::Speed => format!("Speed    < {} ipf >", ipf),
+            Item::Quirks => format!("Quirks   < {} >", self.quirks.name()),
+            Item::Keymap => format!("Keymap   < {} >", self.keymap.name()),
+            Item::Reset => "Reset".to_string(),
+            Item::Exit => "Exit".to_string(),
+        }
+    }
+
+    // a panel in the middle of the window, the selected item inverted
+    fn draw(&self, display: &mut DisplayDriver, ipf: u32) -> Result<(), Box<dyn error::Error>> {
+        let (width, height) = display.size();
+        let palette = *display.palette();
+        let text_scale = display.text_scale() as i32;
+        let line = display.line_height() as i32;
+        let char_width = display.char_width() as i32;
+
+        let labels: Vec<String> = ITEMS.iter().map(|&item| self.label(item, ipf)).collect();
+        let columns = labels.iter().map(|l| l.len()).max().unwrap_or(0) as i32;
+
+        let panel_width = (columns + 4) * char_width;
+        let panel_height = (labels.len() as i32 + 3) * line;
+        let left = (width as i32 - panel_width) / 2;
+        let top = (height as i32 - panel_height) / 2;
+        display.fill_rect(
+            left,
+            top,
+            panel_width as u32,
+            panel_height as u32,
+            palette.background,
+            224,
+        )?;
+
+        let x = left + 2 * char_width;
+        display.draw_text(x, top + line, "PAUSED", palette.pixel)?;
+
+        for (i, label) in labels.iter().enumerate() {
+            let y = top + (i as i32 + 2) * line + line / 2;
+            if i == self.selected {
+                display.fill_rect(
+                    x - text_scale * 2,
+                    y - text_scale * 2,
+                    ((columns + 1) * char_width) as u32,
+                    line as u32,
+                    palette.pixel,
+                    255,
+                )?;
+                display.draw_text(x, y, label, palette.background)?;
+            } else {
+                display.draw_text(x, y, label, palette.pixel)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<T: Clone + PartialEq> Choice<T> {
+    // a current value that is not one of the presets is kept as "custom"
+    fn new(current: &T, mut options: Vec<(String, T)>) -> Self {
+        let index = match options.iter().position(|(_, value)| value == current) {
+            Some(index) => index,
+            None => {
+                options.insert(0, ("custom".to_string(), current.clone()));
+                0
+            }
+        };
+
+        Choice { options, index }
+    }
+
+    fn step(&mut self, forward: bool) -> T {
+        let count = self.options.len();
+        self.index = if forward {
+            (self.index + 1) % count
+        } else {
+            (self.index + count - 1) % count
+        };
+        self.options[self.index].1.clone()
+    }
+
+    fn name(&self) -> &str {
+        &self.options[self.index].0
+    }
+}
+
+// text on a translucent panel so it stays readable over the display
+fn draw_label(
+    display: &mut DisplayDriver,
+    x: i32,
+    y: i32,
+    text: &str,
+) -> Result<(), Box<dyn error::Error>> {
+    let palette = *display.palette();
+    let padding = display.text_scale() as i32 * 2;
+    let text_width = text.chars().count() as u32 * display.char_width();
+
+    display.fill_rect(
+        x - padding,
+        y - padding,
+        text_width + padding as u32,
+        display.line_height(),
+        palette.background,
+        192,
+    )?;
+    display.draw_text(x, y, text, palette.pixel)
+}
@@ -0,0 +1,81 @@
+use std::fmt;
+use std::str::FromStr;
+
+// colors used to draw the two pixel states
+#[derive(Clone, Copy, PartialEq)]
+pub struct Palette {
+    pub background: (u8, u8, u8),
+    pub pixel: (u8, u8, u8),
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            background: (0, 0, 0),
+            pixel: (173, 140, 255),
+        }
+    }
+}
+
+impl Palette {
+    // palettes that can be given by name
+    pub const NAMES: [&'static str; 5] = ["default", "green", "amber", "gray", "white"];
+
+    // rgb palette for indexed images, index 0 is the background
+    pub fn indexed_colors(&self) -> [u8; 6] {
+        [
+            self.background.0,
+            self.background.1,
+            self.background.2,
+            self.pixel.0,
+            self.pixel.1,
+            self.pixel.2,
+        ]
+    }
+}
+
+// a named palette, or two hex colors as "<background>,<pixel>"
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (background, pixel) = match s {
+            "default" => return Ok(Palette::default()),
+            "green" => ((15, 56, 15), (155, 188, 15)),
+            "amber" => ((40, 20, 0), (255, 176, 0)),
+            "gray" => ((34, 34, 34), (187, 187, 187)),
+            "white" => ((0, 0, 0), (255, 255, 255)),
+            _ => {
+                let colors: Vec<&str> = s.split(',').collect();
+                match colors.as_slice() {
+                    [background, pixel] => (parse_color(background)?, parse_color(pixel)?),
+                    _ => return Err(format!("unknown palette '{}'", s)),
+                }
+            }
+        };
+
+        Ok(Palette { background, pixel })
+    }
+}
+
+impl fmt::Display for Palette {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (b, p) = (self.background, self.pixel);
+        write!(
+            f,
+            "{:02x}{:02x}{:02x},{:02x}{:02x}{:02x}",
+            b.0, b.1, b.2, p.0, p.1, p.2
+        )
+    }
+}
+
+// RRGGBB, with or without a leading '#'
+fn parse_color(s: &str) -> Result<(u8, u8, u8), String> {
+    let hex = s.trim_start_matches('#');
+    let value = match u32::from_str_radix(hex, 16) {
+        Ok(x) if hex.len() == 6 => x,
+        _ => return Err(format!("invalid color '{}'", s)),
+    };
+
+    Ok(((value >> 16) as u8, (value >> 8) as u8, value as u8))
+}
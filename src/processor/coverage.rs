@@ -0,0 +1,194 @@
+use super::disassembler::disassemble;
+use super::symbols::Symbols;
+use std::error;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+const HEATMAP_COLUMNS: usize = 64; // bytes per row, 64 rows cover all of memory
+const HEATMAP_SCALE: usize = 8; // image pixels per byte
+
+// how each byte of memory was used: executed as an instruction, read as data
+// by draw, fx65 and the xo-chip audio pattern, or written by fx33 and fx55
+#[derive(Clone)]
+pub struct Coverage {
+    executed: Vec<u64>, // instructions starting at each address
+    read: Vec<u64>,
+    written: Vec<u64>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Coverage {
+            executed: vec![0; 4096],
+            read: vec![0; 4096],
+            written: vec![0; 4096],
+        }
+    }
+
+    pub fn execute(&mut self, address: usize) {
+        self.executed[address % 4096] += 1;
+    }
+
+    pub fn read(&mut self, address: usize, length: usize) {
+        for i in address..address + length {
+            self.read[i % 4096] += 1;
+        }
+    }
+
+    pub fn write(&mut self, address: usize, length: usize) {
+        for i in address..address + length {
+            self.written[i % 4096] += 1;
+        }
+    }
+
+    // an lcov tracefile for a disassembly of the program, which is written
+    // next to it with the extension .asm. bytes that were only used as data
+    // are left out of the line counts, everything else that never ran is
+    // reported as a missed line
+    pub fn write_lcov(
+        &self,
+        filename: &str,
+        memory: &[u8; 4096],
+        program: (usize, usize), // start and length
+        symbols: &Symbols,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let listing_path = Path::new(filename).with_extension("asm");
+        let mut listing = String::new();
+        let mut lines = String::new();
+        let mut functions = String::new();
+        let (mut found, mut hit) = (0, 0);
+        let (mut functions_found, mut functions_hit) = (0, 0);
+
+        // code copied outside of the program still shows up
+        let (start, length) = program;
+        let last_executed = (0..4096).rev().find(|&a| self.executed[a] > 0);
+        let end = last_executed.map_or(start + length, |a| (a + 2).max(start + length));
+
+        let mut address = start;
+        let mut line = 0;
+        while address < end.min(4096) {
+            line += 1;
+
+            // a byte before an instruction that starts at an odd address
+            let single = self.executed[address] == 0
+                && self.executed.get(address + 1).is_some_and(|&c| c > 0);
+            let width = if single { 1 } else { 2 };
+
+            let opcode = (memory[address] as u16) << 8 | memory[(address + 1) % 4096] as u16;
+            let text = if single {
+                format!(
+                    "{:#05x}  {:02x}    DB {:#04x}",
+                    address, memory[address], memory[address]
+                )
+            } else {
+                format!("{:#05x}  {:04x}  {}", address, opcode, disassemble(opcode))
+            };
+
+            let bytes = address..(address + width).min(4096);
+            let reads: u64 = bytes.clone().map(|a| self.read[a]).sum();
+            let writes: u64 = bytes.map(|a| self.written[a]).sum();
+            let executions = self.executed[address];
+
+            if let Some(label) = symbols.label(address as u16).filter(|l| !l.contains('+')) {
+                listing += &format!("{}:\n", label);
+                line += 1;
+                functions += &format!("FN:{},{}\nFNDA:{},{}\n", line, label, executions, label);
+                functions_found += 1;
+                if executions > 0 {
+                    functions_hit += 1;
+                }
+            }
+
+            listing += &text;
+            if reads > 0 || writes > 0 {
+                listing += &format!("  ; read {} written {}", reads, writes);
+            }
+            listing += "\n";
+
+            let data = executions == 0 && (reads > 0 || writes > 0);
+            if !data {
+                lines += &format!("DA:{},{}\n", line, executions);
+                found += 1;
+                if executions > 0 {
+                    hit += 1;
+                }
+            }
+
+            address += width;
+        }
+
+        fs::write(&listing_path, listing)
+            .map_err(|e| format!("could not write '{}': {}", listing_path.display(), e))?;
+
+        let source = fs::canonicalize(&listing_path).unwrap_or(listing_path);
+        let tracefile = format!(
+            "TN:\nSF:{}\n{}FNF:{}\nFNH:{}\n{}LF:{}\nLH:{}\nend_of_record\n",
+            source.display(),
+            functions,
+            functions_found,
+            functions_hit,
+            lines,
+            found,
+            hit
+        );
+
+        fs::write(filename, tracefile)
+            .map_err(|e| format!("could not write '{}': {}", filename, e).into())
+    }
+
+    // a png of all 4096 bytes, 64 to a row. green is execution, blue reads and
+    // red writes, brighter for bytes used more often
+    pub fn write_heatmap(&self, filename: &str) -> Result<(), Box<dyn error::Error>> {
+        let size = HEATMAP_COLUMNS * HEATMAP_SCALE;
+        let mut pixels = vec![0; size * size * 3];
+
+        let executed = self.executed_bytes();
+        let channels = [(&self.written, 0), (&executed, 1), (&self.read, 2)];
+
+        for (counts, channel) in channels {
+            let max = counts.iter().copied().max().unwrap_or(0);
+            for (address, &count) in counts.iter().enumerate() {
+                let value = intensity(count, max);
+                let (column, row) = (address % HEATMAP_COLUMNS, address / HEATMAP_COLUMNS);
+
+                for y in row * HEATMAP_SCALE..(row + 1) * HEATMAP_SCALE {
+                    for x in column * HEATMAP_SCALE..(column + 1) * HEATMAP_SCALE {
+                        pixels[(y * size + x) * 3 + channel] = value;
+                    }
+                }
+            }
+        }
+
+        let file = File::create(filename)
+            .map_err(|e| format!("could not create '{}': {}", filename, e))?;
+        let mut encoder = png::Encoder::new(io::BufWriter::new(file), size as u32, size as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    // both bytes of each executed instruction
+    fn executed_bytes(&self) -> Vec<u64> {
+        let mut bytes = vec![0; 4096];
+        for (address, &count) in self.executed.iter().enumerate() {
+            bytes[address] += count;
+            bytes[(address + 1) % 4096] += count;
+        }
+        bytes
+    }
+}
+
+// log scaled so bytes used once are still visible next to hot loops
+fn intensity(count: u64, max: u64) -> u8 {
+    if count == 0 {
+        return 0;
+    }
+    let scaled = (count as f64).ln_1p() / (max as f64).ln_1p();
+    (64.0 + 191.0 * scaled) as u8
+}
@@ -0,0 +1,81 @@
+use super::cpu::*;
+use super::symbols::Symbols;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// set I to the byte after the code, draw it, store a bcd over it, then loop
+const PROGRAM: [u8; 11] = [
+    0xa2, 0x0a, 0xd0, 0x11, 0xf0, 0x33, 0x12, 0x06, 0x12, 0x00, 0xff,
+];
+
+// tests run in parallel, so every report gets a file of its own
+fn temp_path(test: &str, extension: &str) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    env::temp_dir().join(format!(
+        "chip8-{}-{}-{}.{}",
+        test,
+        std::process::id(),
+        count,
+        extension
+    ))
+}
+
+fn run(program: &[u8]) -> Cpu {
+    let options = CpuOptions {
+        coverage: true,
+        ..CpuOptions::default()
+    };
+    let mut cpu = match Cpu::with_options(program, &options) {
+        Ok(x) => x,
+        Err(e) => panic!("{}", e),
+    };
+    cpu.run_frame(&[false; 16], 10).unwrap();
+    cpu
+}
+
+#[test]
+fn test_coverage_lcov() {
+    let cpu = run(&PROGRAM);
+    let path = temp_path("coverage_lcov", "info");
+    let listing_path = path.with_extension("asm");
+    let symbols = Symbols::parse("0x200 main\n0x208 unused").unwrap();
+
+    let coverage = cpu.coverage.as_ref().unwrap();
+    coverage
+        .write_lcov(
+            path.to_str().unwrap(),
+            cpu.memory(),
+            (0x200, PROGRAM.len()),
+            &symbols,
+        )
+        .unwrap();
+    let tracefile = fs::read_to_string(&path).unwrap();
+    let listing = fs::read_to_string(&listing_path).unwrap();
+    fs::remove_file(&path).unwrap();
+    fs::remove_file(&listing_path).unwrap();
+
+    // the data byte is left out, the jump after the loop was never run
+    assert!(tracefile.contains("LF:5\nLH:4\n"));
+    assert!(tracefile.contains("FNDA:1,main\n"));
+    assert!(tracefile.contains("FNDA:0,unused\n"));
+    assert!(listing.contains("0x206  1206  JP 0x206\n"));
+    // the bcd of V0 = 0 has overwritten the sprite
+    assert!(listing.contains("0x20a  0000"));
+    assert!(listing.contains("; read 1 written 2\n"));
+}
+
+#[test]
+fn test_coverage_heatmap() {
+    let cpu = run(&PROGRAM);
+    let path = temp_path("coverage_heatmap", "png");
+
+    let coverage = cpu.coverage.as_ref().unwrap();
+    coverage.write_heatmap(path.to_str().unwrap()).unwrap();
+    let image = fs::read(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(&image[1..4], b"PNG");
+}
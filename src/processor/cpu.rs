@@ -1,15 +1,24 @@
+use super::coverage::Coverage;
+use super::disassembler::disassemble;
 use super::fontset::FONTSET;
+use super::framebuffer::Framebuffer;
+use super::instruction_cache::{Decoded, InstructionCache};
 use super::operations;
+use super::profiler::Profiler;
+use super::quirks::Quirks;
+use super::trace::Tracer;
 use super::CHIP8_HEIGHT;
-use super::CHIP8_WIDTH;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
 use std::error;
 
+#[derive(Clone)]
 pub struct Cpu {
-    pub memory: [u8; 4096],
+    memory: [u8; 4096], // read with memory(), written with write_memory()
     pub register: [u8; 16],
     pub register_i: u16, // memory location register
     pub program_counter: usize,
-    pub display: [u8; (CHIP8_WIDTH * CHIP8_HEIGHT) as usize], // display, 64*32 pixels, 0=black, 1=white
+    pub display: Framebuffer, // display, 32 rows of 64 pixels, bit set=white
     pub delay_timer: u8,
     pub sound_timer: u8,
     pub stack: [u16; 16],
@@ -18,21 +27,74 @@ pub struct Cpu {
     pub redraw_flag: bool,
     pub keypad_waiting: bool,
     pub keypad_waiting_register: u8,
+    pub audio_pattern: [u8; 16], // xo-chip 1-bit audio pattern, 128 samples
+    pub pitch: u8,               // xo-chip pattern playback rate
+    pub audio_pattern_loaded: bool, // f002 ran, until then the beep is played
+    pub audio_flag: bool,
+    pub quirks: Quirks,
+    pub rng: StdRng,
+    pub debug: bool, // print each instruction before it is executed
+    pub profiler: Option<Profiler>,
+    pub coverage: Option<Coverage>,
+    pub tracer: Option<Tracer>,
+    pub cycles: u64,           // instructions executed since the program started
+    pub fault: Option<String>, // set by an instruction that can't run, returned by cycle
+    cache: InstructionCache,   // decoded from memory, kept in step by write_memory()
+    rewritten: [u64; 64],      // a bit per address whose instruction was written over
+}
+
+#[derive(Clone, Copy)]
+pub struct CpuOptions {
+    pub quirks: Quirks,
+    pub start_address: usize, // where the program is loaded and execution starts
+    pub seed: Option<u64>,    // seed for CXNN, random if not set
+    pub debug: bool,
+    pub profile: bool,  // count executed instructions for a report
+    pub coverage: bool, // track how each byte of memory is used
 }
 
 pub struct CycleOutput {
     pub redraw_flag: bool,
-    pub sound_flag: bool,
+    pub audio_flag: bool, // audio pattern or pitch changed
+}
+
+pub struct FrameOutput {
+    pub redraw_flag: bool,
+    pub sound_flag: bool, // sound timer was active during the frame
+    pub audio_flag: bool,
 }
 
 impl Cpu {
     pub fn new(program: &Vec<u8>) -> Result<Self, Box<dyn error::Error>> {
+        Self::with_options(program, &CpuOptions::default())
+    }
+
+    pub fn with_options(
+        program: &[u8],
+        options: &CpuOptions,
+    ) -> Result<Self, Box<dyn error::Error>> {
+        let start = options.start_address;
+        if start >= 4096 || program.len() > 4096 - start {
+            return Err(format!(
+                "program is {} bytes, only {} fit in memory starting at {:#x}",
+                program.len(),
+                4096usize.saturating_sub(start),
+                start
+            )
+            .into());
+        }
+
+        let rng = match options.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
         let mut cpu = Self {
             memory: [0; 4096],
             register: [0; 16],
             register_i: 0,
-            program_counter: 0x200,
-            display: [0; (CHIP8_WIDTH * CHIP8_HEIGHT) as usize],
+            program_counter: start,
+            display: [0; CHIP8_HEIGHT as usize],
             delay_timer: 0,
             sound_timer: 0,
             stack: [0; 16],
@@ -41,6 +103,20 @@ impl Cpu {
             redraw_flag: false,
             keypad_waiting: false,
             keypad_waiting_register: 0,
+            audio_pattern: [0; 16],
+            pitch: 64,
+            audio_pattern_loaded: false,
+            audio_flag: false,
+            quirks: options.quirks,
+            rng,
+            debug: options.debug,
+            profiler: options.profile.then(Profiler::new),
+            coverage: options.coverage.then(Coverage::new),
+            tracer: None,
+            cycles: 0,
+            fault: None,
+            cache: InstructionCache::new(),
+            rewritten: [0; 64],
         };
 
         // load fontset into memory
@@ -50,7 +126,7 @@ impl Cpu {
 
         // load program into memory
         for i in 0..program.len() {
-            cpu.memory[i + 0x200] = program[i];
+            cpu.memory[i + start] = program[i];
         }
 
         Ok(cpu)
@@ -62,7 +138,7 @@ impl Cpu {
         }
 
         let mut redraw_flag = false;
-        let mut sound_flag = false;
+        let mut audio_flag = false;
 
         if self.keypad_waiting {
             for i in 0..self.keypad.len() {
@@ -73,25 +149,185 @@ impl Cpu {
                 }
             }
         } else {
-            operations::execute_instruction(self);
+            if self.debug {
+                eprintln!("{}", self.current_instruction());
+            }
 
-            if self.delay_timer > 0 {
-                self.delay_timer -= 1;
+            if self.instrumented() {
+                operations::execute_instruction(self);
+            } else {
+                operations::execute_uninstrumented(self);
             }
 
-            if self.sound_timer > 0 {
-                self.sound_timer -= 1;
-                sound_flag = true;
+            // the program counter is left on the instruction that failed
+            if self.fault.is_some() {
+                return Err(self.take_fault());
             }
 
             redraw_flag = self.redraw_flag;
             self.redraw_flag = false;
-            self.program_counter += 2;
+            audio_flag = self.audio_flag;
+            self.audio_flag = false;
+            self.program_counter = (self.program_counter + 2) % 4096;
         }
 
         Ok(CycleOutput {
             redraw_flag: redraw_flag,
-            sound_flag: sound_flag,
+            audio_flag: audio_flag,
+        })
+    }
+
+    // run one 60hz frame, the given number of instructions followed by a timer tick
+    pub fn run_frame(
+        &mut self,
+        key_state: &[bool; 16],
+        instructions: u32,
+    ) -> Result<FrameOutput, Box<dyn error::Error>> {
+        if self.instrumented() {
+            self.run_frame_with(key_state, instructions, operations::execute_instruction)
+        } else {
+            self.run_frame_with(key_state, instructions, operations::execute_uninstrumented)
+        }
+    }
+
+    // run a frame with instructions executed by something other than the
+    // interpreter, such as a recompiled program. execute is called with the
+    // program counter on the instruction to run and works like the
+    // interpreter's operations: the program counter is moved on by 2 after it
+    // returns, so jumps set it 2 before their target
+    pub fn run_frame_with<F: FnMut(&mut Cpu)>(
+        &mut self,
+        key_state: &[bool; 16],
+        instructions: u32,
+        mut execute: F,
+    ) -> Result<FrameOutput, Box<dyn error::Error>> {
+        let mut redraw_flag = false;
+        let mut audio_flag = false;
+        self.keypad.copy_from_slice(key_state);
+
+        for _ in 0..instructions {
+            // waiting for a key and printing instructions go the slow way
+            if self.keypad_waiting || self.debug {
+                let cycle_output = self.cycle(key_state)?;
+                redraw_flag |= cycle_output.redraw_flag;
+                audio_flag |= cycle_output.audio_flag;
+                continue;
+            }
+
+            execute(self);
+            if self.fault.is_some() {
+                return Err(self.take_fault());
+            }
+            self.program_counter = (self.program_counter + 2) % 4096;
+        }
+
+        // instructions only ever set the flags, so they can be collected once
+        redraw_flag |= self.redraw_flag;
+        audio_flag |= self.audio_flag;
+        self.redraw_flag = false;
+        self.audio_flag = false;
+
+        if let Some(profiler) = &mut self.profiler {
+            profiler.end_frame();
+        }
+
+        let sound_flag = self.sound_timer > 0;
+        self.tick_timers();
+
+        Ok(FrameOutput {
+            redraw_flag,
+            sound_flag,
+            audio_flag,
         })
     }
+
+    // whether anything counts or logs instructions, they skip the hooks if not
+    pub fn instrumented(&self) -> bool {
+        self.profiler.is_some() || self.coverage.is_some() || self.tracer.is_some()
+    }
+
+    // checked with is_some first, taking it every instruction costs a write
+    fn take_fault(&mut self) -> Box<dyn error::Error> {
+        self.fault.take().unwrap_or_default().into()
+    }
+
+    // the instruction at the program counter, as address, opcode and mnemonic
+    pub fn current_instruction(&self) -> String {
+        let pc = self.program_counter;
+        let opcode = self.opcode_at(pc);
+        format!("{:03x}: {:04x}  {}", pc, opcode, disassemble(opcode))
+    }
+
+    pub fn opcode_at(&self, address: usize) -> u16 {
+        (self.memory[address % 4096] as u16) << 8 | self.memory[(address + 1) % 4096] as u16
+    }
+
+    // run the instruction at the program counter with the interpreter, for
+    // recompiled programs when they reach code they have no translation for
+    pub fn execute(&mut self) {
+        operations::execute_instruction(self);
+    }
+
+    // memory used as data by an instruction, for coverage
+    pub fn track_read(&mut self, address: usize, length: usize) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.read(address, length);
+        }
+    }
+
+    pub fn memory(&self) -> &[u8; 4096] {
+        &self.memory
+    }
+
+    // every write to memory goes through here so instructions that were
+    // decoded from it are decoded again
+    pub fn write_memory(&mut self, address: usize, value: u8) {
+        self.memory[address % 4096] = value;
+        self.cache.invalidate(address);
+        for start in [address % 4096, (address + 4095) % 4096] {
+            self.rewritten[start / 64] |= 1 << (start % 64);
+        }
+    }
+
+    // whether a byte of the instruction at address was written since the
+    // program was loaded, recompiled programs interpret those
+    pub fn rewritten(&self, address: usize) -> bool {
+        self.rewritten[address / 64] & 1 << (address % 64) != 0
+    }
+
+    // the instruction at the program counter, decoded
+    pub(super) fn decode(&mut self) -> Decoded {
+        self.cache.get(&self.memory, self.program_counter)
+    }
+
+    pub fn track_write(&mut self, address: usize, length: usize) {
+        if let Some(coverage) = &mut self.coverage {
+            coverage.write(address, length);
+        }
+    }
+
+    // the delay and sound timers count down at 60hz on every chip8, whatever
+    // the instruction rate, so they tick once per frame
+    pub fn tick_timers(&mut self) {
+        if self.delay_timer > 0 {
+            self.delay_timer -= 1;
+        }
+
+        if self.sound_timer > 0 {
+            self.sound_timer -= 1;
+        }
+    }
+}
+
+impl Default for CpuOptions {
+    fn default() -> Self {
+        CpuOptions {
+            quirks: Quirks::default(),
+            start_address: 0x200,
+            seed: None,
+            debug: false,
+            profile: false,
+            coverage: false,
+        }
+    }
 }
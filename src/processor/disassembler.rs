@@ -0,0 +1,53 @@
+// assembly style mnemonic for an instruction, as in cowgod's technical reference
+pub fn disassemble(opcode: u16) -> String {
+    let nibbles = (
+        (opcode >> 12) as u8,
+        ((opcode >> 8) & 0xf) as u8,
+        ((opcode >> 4) & 0xf) as u8,
+        (opcode & 0xf) as u8,
+    );
+    let address = opcode & 0xfff;
+    let byte = opcode & 0xff;
+    let x = nibbles.1;
+    let y = nibbles.2;
+
+    match nibbles {
+        (0x0, 0x0, 0xe, 0x0) => "CLS".to_string(),
+        (0x0, 0x0, 0xe, 0xe) => "RET".to_string(),
+        (0x1, _, _, _) => format!("JP {:#05x}", address),
+        (0x2, _, _, _) => format!("CALL {:#05x}", address),
+        (0x3, _, _, _) => format!("SE V{:X}, {:#04x}", x, byte),
+        (0x4, _, _, _) => format!("SNE V{:X}, {:#04x}", x, byte),
+        (0x5, _, _, 0x0) => format!("SE V{:X}, V{:X}", x, y),
+        (0x6, _, _, _) => format!("LD V{:X}, {:#04x}", x, byte),
+        (0x7, _, _, _) => format!("ADD V{:X}, {:#04x}", x, byte),
+        (0x8, _, _, 0x0) => format!("LD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x1) => format!("OR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x2) => format!("AND V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x3) => format!("XOR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x4) => format!("ADD V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x5) => format!("SUB V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x6) => format!("SHR V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0x7) => format!("SUBN V{:X}, V{:X}", x, y),
+        (0x8, _, _, 0xe) => format!("SHL V{:X}, V{:X}", x, y),
+        (0x9, _, _, 0x0) => format!("SNE V{:X}, V{:X}", x, y),
+        (0xa, _, _, _) => format!("LD I, {:#05x}", address),
+        (0xb, _, _, _) => format!("JP V0, {:#05x}", address),
+        (0xc, _, _, _) => format!("RND V{:X}, {:#04x}", x, byte),
+        (0xd, _, _, n) => format!("DRW V{:X}, V{:X}, {}", x, y, n),
+        (0xe, _, 0x9, 0xe) => format!("SKP V{:X}", x),
+        (0xe, _, 0xa, 0x1) => format!("SKNP V{:X}", x),
+        (0xf, 0x0, 0x0, 0x2) => "AUDIO".to_string(),
+        (0xf, _, 0x0, 0x7) => format!("LD V{:X}, DT", x),
+        (0xf, _, 0x0, 0xa) => format!("LD V{:X}, K", x),
+        (0xf, _, 0x1, 0x5) => format!("LD DT, V{:X}", x),
+        (0xf, _, 0x1, 0x8) => format!("LD ST, V{:X}", x),
+        (0xf, _, 0x1, 0xe) => format!("ADD I, V{:X}", x),
+        (0xf, _, 0x2, 0x9) => format!("LD F, V{:X}", x),
+        (0xf, _, 0x3, 0x3) => format!("LD B, V{:X}", x),
+        (0xf, _, 0x3, 0xa) => format!("PITCH V{:X}", x),
+        (0xf, _, 0x5, 0x5) => format!("LD [I], V{:X}", x),
+        (0xf, _, 0x6, 0x5) => format!("LD V{:X}, [I]", x),
+        _ => format!("DW {:#06x}", opcode),
+    }
+}
@@ -0,0 +1,47 @@
+use super::CHIP8_HEIGHT;
+use super::CHIP8_WIDTH;
+
+const WIDTH: usize = CHIP8_WIDTH as usize;
+const HEIGHT: usize = CHIP8_HEIGHT as usize;
+
+// one row of the display, the leftmost pixel in the highest bit
+pub type Row = u64;
+
+// the display as packed rows, so clearing, copying and comparing it are a
+// few words instead of a byte per pixel
+pub type Framebuffer = [Row; HEIGHT];
+
+pub fn pixel(display: &Framebuffer, x: usize, y: usize) -> bool {
+    display[y] & (1 << (WIDTH - 1 - x)) != 0
+}
+
+// xor a sprite row onto row y starting at column x, the part past the right
+// edge is cut off. returns whether a lit pixel was turned off
+pub fn xor_clipped(display: &mut Framebuffer, x: usize, y: usize, bits: u8) -> bool {
+    let sprite = (bits as Row) << (WIDTH - 8) >> x;
+    let collision = display[y] & sprite != 0;
+    display[y] ^= sprite;
+    collision
+}
+
+// xor a sprite row onto the display treated as one line of pixels: the part
+// past the right edge continues at the start of the next row, and the bottom
+// row continues at the top
+pub fn xor_wrapped(display: &mut Framebuffer, index: usize, bits: u8) -> bool {
+    let index = index % (WIDTH * HEIGHT);
+    let (x, y) = (index % WIDTH, index / WIDTH);
+    let sprite = (bits as Row) << (WIDTH - 8);
+
+    let mut collision = xor_row(display, y, sprite >> x);
+    // only a sprite starting in the last 7 columns reaches the next row
+    if x > WIDTH - 8 {
+        collision |= xor_row(display, (y + 1) % HEIGHT, sprite << (WIDTH - x));
+    }
+    collision
+}
+
+fn xor_row(display: &mut Framebuffer, y: usize, sprite: Row) -> bool {
+    let collision = display[y] & sprite != 0;
+    display[y] ^= sprite;
+    collision
+}
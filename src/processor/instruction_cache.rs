@@ -0,0 +1,48 @@
+use super::operation_map::function_from_instruction;
+use super::operations::Opcode;
+use super::Cpu;
+
+// an instruction with the function that runs it
+#[derive(Clone, Copy)]
+pub struct Decoded {
+    pub function: fn(&mut Cpu, &Opcode),
+    pub opcode: Opcode,
+}
+
+// decoded instructions by address, so an instruction is only looked up in the
+// opcode tables the first time it runs. writes to memory clear the entries
+// they overlap, which keeps self-modifying code working
+#[derive(Clone)]
+pub struct InstructionCache {
+    entries: Box<[Option<Decoded>]>,
+}
+
+impl InstructionCache {
+    pub fn new() -> Self {
+        InstructionCache {
+            entries: vec![None; 4096].into_boxed_slice(),
+        }
+    }
+
+    pub fn get(&mut self, memory: &[u8; 4096], address: usize) -> Decoded {
+        let address = address % 4096;
+        match self.entries[address] {
+            Some(decoded) => decoded,
+            None => {
+                let opcode = Opcode::from_memory(memory, address);
+                let decoded = Decoded {
+                    function: function_from_instruction(&opcode),
+                    opcode,
+                };
+                self.entries[address] = Some(decoded);
+                decoded
+            }
+        }
+    }
+
+    // a byte changed, it is the second half of an instruction starting just before it
+    pub fn invalidate(&mut self, address: usize) {
+        self.entries[address % 4096] = None;
+        self.entries[(address + 4095) % 4096] = None;
+    }
+}
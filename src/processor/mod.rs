@@ -1,12 +1,40 @@
+mod coverage;
 mod cpu;
+mod disassembler;
 mod fontset;
+mod framebuffer;
+mod instruction_cache;
 mod operation_map;
-mod operations;
+pub mod operations; // called directly by recompiled programs
+mod profiler;
+mod quirks;
+mod recompiler;
+mod symbols;
+mod trace;
 
+#[cfg(test)]
+mod coverage_tests;
 #[cfg(test)]
 mod operation_tests;
+#[cfg(test)]
+mod profiler_tests;
+#[cfg(test)]
+mod property_tests;
+#[cfg(test)]
+mod quirks_tests;
+#[cfg(test)]
+mod recompiler_tests;
+#[cfg(test)]
+mod trace_tests;
 
 pub use self::cpu::Cpu;
+pub use self::cpu::CpuOptions;
+pub use self::cpu::FrameOutput;
+pub use self::framebuffer::{pixel, Framebuffer, Row};
+pub use self::quirks::Quirks;
+pub use self::recompiler::recompile;
+pub use self::symbols::Symbols;
+pub use self::trace::{compare_lines, trace_header, Difference, TraceFile, TraceOptions, Tracer};
 
 pub const CHIP8_WIDTH: u32 = 64;
 pub const CHIP8_HEIGHT: u32 = 32;
@@ -1,15 +1,17 @@
 use super::operations;
-use crate::processor::operations::Opcode;
-use crate::Cpu;
+use super::operations::Opcode;
+use super::Cpu;
 
 pub fn function_from_instruction(ins: &Opcode) -> fn(&mut Cpu, &Opcode) {
-    match ins.nibbles().0 {
-        0x0 => FUNCTION_MAP_0[ins.nibbles().3 as usize],
-        0x8 => FUNCTION_MAP_8[ins.nibbles().3 as usize],
-        0xe => FUNCTION_MAP_E[ins.nibbles().3 as usize],
-        0xf => FUNCTION_MAP_F[ins.bytes().1 as usize],
-        _ => FUNCTION_MAP[ins.nibbles().0 as usize],
-    }
+    let function = match ins.nibbles().0 {
+        0x0 => FUNCTION_MAP_0.get(ins.nibbles().3 as usize),
+        0x8 => FUNCTION_MAP_8.get(ins.nibbles().3 as usize),
+        0xe => FUNCTION_MAP_E.get(ins.nibbles().3 as usize),
+        0xf => FUNCTION_MAP_F.get(ins.bytes().1 as usize),
+        _ => FUNCTION_MAP.get(ins.nibbles().0 as usize),
+    };
+    // the tables stop after the last instruction they know
+    *function.unwrap_or(&(operations::unknown_instruction as fn(&mut Cpu, &Opcode)))
 }
 
 const FUNCTION_MAP: [fn(&mut Cpu, &Opcode); 16] = [
@@ -88,7 +90,7 @@ const FUNCTION_MAP_E: [fn(&mut Cpu, &Opcode); 15] = [
 const FUNCTION_MAP_F: [fn(&mut Cpu, &Opcode); 102] = [
     operations::unknown_instruction,
     operations::unknown_instruction,
-    operations::unknown_instruction,
+    operations::load_audio_pattern,
     operations::unknown_instruction,
     operations::unknown_instruction,
     operations::unknown_instruction,
@@ -144,7 +146,7 @@ const FUNCTION_MAP_F: [fn(&mut Cpu, &Opcode); 102] = [
     operations::unknown_instruction,
     operations::unknown_instruction,
     operations::unknown_instruction,
-    operations::unknown_instruction,
+    operations::set_pitch,
     operations::unknown_instruction,
     operations::unknown_instruction,
     operations::unknown_instruction,
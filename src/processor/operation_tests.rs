@@ -1,8 +1,8 @@
 use super::cpu::*;
 use super::operation_map::function_from_instruction;
 use super::operations::*;
+use super::pixel;
 use super::CHIP8_HEIGHT;
-use super::CHIP8_WIDTH;
 
 fn setup(bytes: u16) -> (Cpu, Opcode, fn(&mut Cpu, &Opcode)) {
     let empty_program = Vec::<u8>::new();
@@ -22,15 +22,11 @@ fn setup(bytes: u16) -> (Cpu, Opcode, fn(&mut Cpu, &Opcode)) {
 fn test_clear_display() {
     let (mut cpu, instruction, function) = setup(0x00e0);
 
-    for i in 0..CHIP8_HEIGHT * CHIP8_WIDTH {
-        cpu.display[i as usize] = 0x5;
-    }
+    cpu.display = [u64::MAX; CHIP8_HEIGHT as usize];
 
     function(&mut cpu, &instruction);
 
-    for i in 0..(CHIP8_HEIGHT * CHIP8_WIDTH) {
-        assert_eq!(cpu.display[i as usize], 0x0);
-    }
+    assert_eq!(cpu.display, [0; CHIP8_HEIGHT as usize]);
 }
 
 #[test]
@@ -234,6 +230,26 @@ fn test_shift_left() {
     assert_eq!(cpu.register[0xf], 0x1);
 }
 
+#[test]
+fn test_shift_vf() {
+    // shifting VF itself leaves the flag in VF, not the shifted value
+    let (mut cpu, instruction, function) = setup(0x8ff6);
+    cpu.register[0xf] = 0x3;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.register[0xf], 0x1);
+    cpu.register[0xf] = 0x2;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.register[0xf], 0x0);
+
+    let (mut cpu, instruction, function) = setup(0x8ffe);
+    cpu.register[0xf] = 0x81;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.register[0xf], 0x1);
+    cpu.register[0xf] = 0x41;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.register[0xf], 0x0);
+}
+
 #[test]
 fn test_skip_not_equal_registers() {
     let (mut cpu, instruction, function) = setup(0x9270);
@@ -274,7 +290,38 @@ fn test_random_byte() {
 
 #[test]
 fn test_draw() {
-    // todo
+    let (mut cpu, instruction, function) = setup(0xd122);
+    cpu.register_i = 0x50;
+    cpu.write_memory(0x50, 0xf0);
+    cpu.write_memory(0x51, 0x81);
+    cpu.register[0x1] = 4;
+    cpu.register[0x2] = 3;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.display[3], 0xf0 << 52);
+    assert_eq!(cpu.display[4], 0x81 << 52);
+    assert_eq!(cpu.register[0xf], 0x0);
+    assert!(pixel(&cpu.display, 4, 3));
+    assert!(!pixel(&cpu.display, 8, 3));
+
+    // drawing it again turns it off and collides
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.display, [0; CHIP8_HEIGHT as usize]);
+    assert_eq!(cpu.register[0xf], 0x1);
+}
+
+#[test]
+fn test_draw_wraps() {
+    // past the right edge onto the next row, and from the bottom to the top
+    let (mut cpu, instruction, function) = setup(0xd121);
+    cpu.register_i = 0x50;
+    cpu.write_memory(0x50, 0xff);
+    cpu.register[0x1] = 60;
+    cpu.register[0x2] = 31;
+    cpu.display[0] = 1 << 63;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.display[31], 0xf);
+    assert_eq!(cpu.display[0], 0x7 << 60);
+    assert_eq!(cpu.register[0xf], 0x1);
 }
 
 #[test]
@@ -287,6 +334,23 @@ fn test_skip_if_not_key() {
     // todo
 }
 
+#[test]
+fn test_load_audio_pattern() {
+    let (mut cpu, instruction, function) = setup(0xf002);
+    assert!(!cpu.audio_pattern_loaded);
+    cpu.register_i = 0x50;
+    for i in 0..16 {
+        cpu.write_memory(0x50 + i, i as u8 * 0x11);
+    }
+    cpu.write_memory(0x60, 0xaa);
+    function(&mut cpu, &instruction);
+    for i in 0..16 {
+        assert_eq!(cpu.audio_pattern[i], i as u8 * 0x11);
+    }
+    assert!(cpu.audio_pattern_loaded);
+    assert!(cpu.audio_flag);
+}
+
 #[test]
 fn test_set_register_to_delay_timer() {
     let (mut cpu, instruction, function) = setup(0xf807);
@@ -322,6 +386,32 @@ fn test_set_sound_timer() {
     assert_eq!(cpu.sound_timer, 0x3);
 }
 
+#[test]
+fn test_timers_tick_at_60hz() {
+    // set both timers to 60, then loop
+    let program = vec![0x60, 0x3c, 0xf0, 0x15, 0xf0, 0x18, 0x12, 0x06];
+
+    // one tick per frame however many instructions it runs, so 60 is a second
+    for instructions in [10, 1000] {
+        let mut cpu = Cpu::new(&program).unwrap();
+        let output = cpu.run_frame(&[false; 16], instructions).unwrap();
+        assert!(output.sound_flag);
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (59, 59));
+
+        for _ in 0..58 {
+            cpu.run_frame(&[false; 16], instructions).unwrap();
+        }
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (1, 1));
+
+        let output = cpu.run_frame(&[false; 16], instructions).unwrap();
+        assert!(output.sound_flag);
+        assert_eq!((cpu.delay_timer, cpu.sound_timer), (0, 0));
+
+        let output = cpu.run_frame(&[false; 16], instructions).unwrap();
+        assert!(!output.sound_flag);
+    }
+}
+
 #[test]
 fn test_add_register_i() {
     let (mut cpu, instruction, function) = setup(0xf81e);
@@ -343,9 +433,21 @@ fn test_store_register_x() {
     cpu.register_i = 0x50;
     cpu.register[0x7] = 0x88; // 136
     function(&mut cpu, &instruction);
-    assert_eq!(cpu.memory[0x50], 0x1);
-    assert_eq!(cpu.memory[0x51], 0x3);
-    assert_eq!(cpu.memory[0x52], 0x6);
+    assert_eq!(cpu.memory()[0x50], 0x1);
+    assert_eq!(cpu.memory()[0x51], 0x3);
+    assert_eq!(cpu.memory()[0x52], 0x6);
+}
+
+#[test]
+fn test_set_pitch() {
+    let (mut cpu, instruction, function) = setup(0xf63a);
+    assert_eq!(cpu.pitch, 64);
+    cpu.register[0x6] = 0x70;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.pitch, 0x70);
+    assert!(cpu.audio_flag);
+    // a pitch alone doesn't replace the beep
+    assert!(!cpu.audio_pattern_loaded);
 }
 
 #[test]
@@ -359,24 +461,24 @@ fn test_store_registers() {
     cpu.register[0x4] = 0x1;
     cpu.register[0x5] = 0xa;
     function(&mut cpu, &instruction);
-    assert_eq!(cpu.memory[0x50], 0x3);
-    assert_eq!(cpu.memory[0x51], 0x8);
-    assert_eq!(cpu.memory[0x52], 0x9);
-    assert_eq!(cpu.memory[0x53], 0xe);
-    assert_eq!(cpu.memory[0x54], 0x1);
-    assert_eq!(cpu.memory[0x55], 0xa);
+    assert_eq!(cpu.memory()[0x50], 0x3);
+    assert_eq!(cpu.memory()[0x51], 0x8);
+    assert_eq!(cpu.memory()[0x52], 0x9);
+    assert_eq!(cpu.memory()[0x53], 0xe);
+    assert_eq!(cpu.memory()[0x54], 0x1);
+    assert_eq!(cpu.memory()[0x55], 0xa);
 }
 
 #[test]
 fn test_read_registers() {
     let (mut cpu, instruction, function) = setup(0xf565);
     cpu.register_i = 0x50;
-    cpu.memory[0x50] = 0x3;
-    cpu.memory[0x51] = 0x8;
-    cpu.memory[0x52] = 0x9;
-    cpu.memory[0x53] = 0xe;
-    cpu.memory[0x54] = 0x1;
-    cpu.memory[0x55] = 0xa;
+    cpu.write_memory(0x50, 0x3);
+    cpu.write_memory(0x51, 0x8);
+    cpu.write_memory(0x52, 0x9);
+    cpu.write_memory(0x53, 0xe);
+    cpu.write_memory(0x54, 0x1);
+    cpu.write_memory(0x55, 0xa);
     function(&mut cpu, &instruction);
     assert_eq!(cpu.register[0x0], 0x3);
     assert_eq!(cpu.register[0x1], 0x8);
@@ -385,3 +487,142 @@ fn test_read_registers() {
     assert_eq!(cpu.register[0x4], 0x1);
     assert_eq!(cpu.register[0x5], 0xa);
 }
+
+#[test]
+fn test_shift_quirk() {
+    let (mut cpu, instruction, function) = setup(0x8346);
+    cpu.quirks.shift_uses_vy = true;
+    cpu.register[0x3] = 0x8;
+    cpu.register[0x4] = 0x3;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.register[0x3], 0x1);
+    assert_eq!(cpu.register[0xf], 0x1);
+}
+
+#[test]
+fn test_logic_quirk() {
+    let (mut cpu, instruction, function) = setup(0x8151);
+    cpu.quirks.logic_resets_vf = true;
+    cpu.register[0xf] = 0x1;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.register[0xf], 0x0);
+}
+
+#[test]
+fn test_load_store_quirk() {
+    let (mut cpu, instruction, function) = setup(0xf555);
+    cpu.quirks.load_store_moves_i = true;
+    cpu.register_i = 0x50;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.register_i, 0x56);
+}
+
+#[test]
+fn test_load_store_by_x_quirk() {
+    let (mut cpu, instruction, function) = setup(0xf565);
+    cpu.quirks.load_store_moves_i = true;
+    cpu.quirks.load_store_moves_i_by_x = true;
+    cpu.register_i = 0x50;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.register_i, 0x55);
+}
+
+#[test]
+fn test_jump_quirk() {
+    let (mut cpu, instruction, function) = setup(0xb87a);
+    cpu.quirks.jump_uses_vx = true;
+    cpu.register[0x0] = 0x8;
+    cpu.register[0x8] = 0x4;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.program_counter, 0x87e);
+}
+
+#[test]
+fn test_clip_quirk() {
+    let (mut cpu, instruction, function) = setup(0xd121);
+    cpu.quirks.clip_sprites = true;
+    cpu.register_i = 0x50;
+    cpu.write_memory(0x50, 0xff);
+    cpu.register[0x1] = 60;
+    cpu.register[0x2] = 0;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.display[0], 0xf);
+    assert_eq!(cpu.display[1], 0x0);
+}
+
+#[test]
+fn test_unknown_instruction() {
+    for bytes in [0x0123, 0x800f, 0xe0a2, 0xf075, 0xf0ff] {
+        let (mut cpu, instruction, function) = setup(bytes);
+        function(&mut cpu, &instruction);
+        assert!(cpu.fault.is_some(), "{:04x}", bytes);
+    }
+}
+
+#[test]
+fn test_stack_limits() {
+    let (mut cpu, instruction, function) = setup(0x2400);
+    cpu.stack_pointer = 15;
+    function(&mut cpu, &instruction);
+    assert!(cpu.fault.is_some());
+    assert_eq!(cpu.stack_pointer, 15);
+
+    let (mut cpu, instruction, function) = setup(0x00ee);
+    function(&mut cpu, &instruction);
+    assert!(cpu.fault.is_some());
+    assert_eq!(cpu.stack_pointer, 0);
+}
+
+#[test]
+fn test_memory_wraps() {
+    let (mut cpu, instruction, function) = setup(0xf233);
+    cpu.register[0x2] = 123;
+    cpu.register_i = 0xfff;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.memory()[0xfff], 1);
+    assert_eq!(cpu.memory()[0x0], 2);
+    assert_eq!(cpu.memory()[0x1], 3);
+
+    let (mut cpu, instruction, function) = setup(0x1000);
+    cpu.program_counter = 0x202;
+    function(&mut cpu, &instruction);
+    assert_eq!(cpu.program_counter, 0xffe);
+}
+
+#[test]
+fn test_self_modifying_code() {
+    // call a subroutine, overwrite its first instruction, call it again
+    let program = vec![
+        0x60, 0x66, // LD V0, 0x66
+        0x61, 0x07, // LD V1, 0x07
+        0x22, 0x0e, // CALL 0x20e
+        0xa2, 0x0e, // LD I, 0x20e
+        0xf1, 0x55, // LD [I], V1
+        0x22, 0x0e, // CALL 0x20e
+        0x12, 0x0c, // JP 0x20c
+        0x65, 0x01, // LD V5, 0x01, becomes LD V6, 0x07
+        0x00, 0xee, // RET
+    ];
+    let mut cpu = Cpu::new(&program).unwrap();
+    cpu.run_frame(&[false; 16], 20).unwrap();
+
+    assert_eq!(cpu.register[0x5], 0x1);
+    assert_eq!(cpu.register[0x6], 0x7);
+}
+
+#[test]
+fn test_write_memory_between_frames() {
+    // a debugger or front end patching a loop the cpu has already decoded
+    let program = vec![
+        0x70, 0x01, // ADD V0, 0x01
+        0x12, 0x00, // JP 0x200
+    ];
+    let mut cpu = Cpu::new(&program).unwrap();
+    cpu.run_frame(&[false; 16], 10).unwrap();
+    assert_eq!(cpu.register[0x0], 5);
+
+    cpu.write_memory(0x200, 0x71);
+    cpu.run_frame(&[false; 16], 10).unwrap();
+    assert_eq!(cpu.register[0x0], 5);
+    assert_eq!(cpu.register[0x1], 5);
+}
@@ -1,20 +1,19 @@
-use super::operation_map::function_from_instruction;
+use super::framebuffer;
+use super::instruction_cache::Decoded;
 use super::Cpu;
 use super::CHIP8_HEIGHT;
 use super::CHIP8_WIDTH;
 use rand::Rng;
 
+#[derive(Clone, Copy)]
 pub struct Opcode {
     instruction_bytes: (u8, u8),
 }
 
 impl Opcode {
-    pub fn from_cpu(cpu: &Cpu) -> Self {
+    pub fn from_memory(memory: &[u8; 4096], address: usize) -> Self {
         Self {
-            instruction_bytes: (
-                cpu.memory[cpu.program_counter],
-                cpu.memory[cpu.program_counter + 1],
-            ),
+            instruction_bytes: (memory[address % 4096], memory[(address + 1) % 4096]),
         }
     }
 
@@ -24,6 +23,10 @@ impl Opcode {
         }
     }
 
+    pub fn value(&self) -> u16 {
+        (self.instruction_bytes.0 as u16) << 8 | self.instruction_bytes.1 as u16
+    }
+
     pub fn bytes(&self) -> (u8, u8) {
         self.instruction_bytes
     }
@@ -61,33 +64,85 @@ impl Opcode {
     }
 }
 
+impl From<u16> for Opcode {
+    fn from(bytes: u16) -> Self {
+        Self::_from_bytes(bytes)
+    }
+}
+
+// run the instruction at pc, counting it for the profiler, coverage and trace
 pub fn execute_instruction(cpu: &mut Cpu) {
-    let ins = Opcode::from_cpu(cpu);
-    function_from_instruction(&ins)(cpu, &ins);
+    let Decoded {
+        function,
+        opcode: ins,
+    } = cpu.decode();
+    if let Some(profiler) = &mut cpu.profiler {
+        profiler.record(cpu.program_counter, ins.value());
+    }
+    if let Some(coverage) = &mut cpu.coverage {
+        coverage.execute(cpu.program_counter);
+    }
+    if let Some(mut tracer) = cpu.tracer.take() {
+        tracer.record(cpu);
+        cpu.tracer = Some(tracer);
+    }
+    cpu.cycles += 1;
+    function(cpu, &ins);
+}
+
+// the same when nothing is counting, most of the time
+pub fn execute_uninstrumented(cpu: &mut Cpu) {
+    let Decoded {
+        function,
+        opcode: ins,
+    } = cpu.decode();
+    cpu.cycles += 1;
+    function(cpu, &ins);
 }
 
 // 0 out display, set redraw flag to 1
 pub fn clear_display(cpu: &mut Cpu, _ins: &Opcode) {
-    cpu.display = [0; (CHIP8_WIDTH * CHIP8_HEIGHT) as usize];
+    cpu.display = [0; CHIP8_HEIGHT as usize];
     cpu.redraw_flag = true;
 }
 
 // set pc to address on top of stack, subtract one from sp
 pub fn return_from_subroutine(cpu: &mut Cpu, _ins: &Opcode) {
+    if cpu.stack_pointer == 0 {
+        cpu.fault = Some(format!(
+            "return with an empty stack at {:#05x}",
+            cpu.program_counter
+        ));
+        return;
+    }
     cpu.program_counter = cpu.stack[cpu.stack_pointer].into();
     cpu.stack_pointer -= 1;
 }
 
 // set pc to addr
 pub fn jump_to_address(cpu: &mut Cpu, ins: &Opcode) {
-    cpu.program_counter = (ins.address() - 2).into(); // subtract 2 to offset pc increment
+    cpu.program_counter = jump_target(ins.address());
 }
 
 // increment sp, push pc to stack, set pc to addr
 pub fn call_subroutine(cpu: &mut Cpu, ins: &Opcode) {
+    if cpu.stack_pointer + 1 >= cpu.stack.len() {
+        cpu.fault = Some(format!("stack overflow at {:#05x}", cpu.program_counter));
+        return;
+    }
     cpu.stack_pointer += 1;
     cpu.stack[cpu.stack_pointer] = cpu.program_counter as u16;
-    cpu.program_counter = (ins.address() - 2).into(); // subtract 2 to offset pc increment
+    cpu.program_counter = jump_target(ins.address());
+}
+
+// subtract 2 to offset pc increment, wrapping around memory
+fn jump_target(address: u16) -> usize {
+    (address as usize).wrapping_sub(2) % 4096
+}
+
+// memory address offset from I, wrapping around memory
+fn address_from_i(cpu: &Cpu, offset: usize) -> usize {
+    (cpu.register_i as usize + offset) % 4096
 }
 
 // if Vx == byte, skip next instruction
@@ -137,16 +192,25 @@ pub fn load_register(cpu: &mut Cpu, ins: &Opcode) {
 // Vx |= Vy
 pub fn bitwise_or(cpu: &mut Cpu, ins: &Opcode) {
     cpu.register[ins.register_x()] |= cpu.register[ins.register_y()];
+    if cpu.quirks.logic_resets_vf {
+        cpu.register[0xf] = 0;
+    }
 }
 
 // Vx &= Vy
 pub fn bitwise_and(cpu: &mut Cpu, ins: &Opcode) {
     cpu.register[ins.register_x()] &= cpu.register[ins.register_y()];
+    if cpu.quirks.logic_resets_vf {
+        cpu.register[0xf] = 0;
+    }
 }
 
 // Vx ^= Vy
 pub fn bitwise_xor(cpu: &mut Cpu, ins: &Opcode) {
     cpu.register[ins.register_x()] ^= cpu.register[ins.register_y()];
+    if cpu.quirks.logic_resets_vf {
+        cpu.register[0xf] = 0;
+    }
 }
 
 // Vx += Vy, if the result is over 255, set VF to 1, otherwise to 0, store the lowest 8 bits
@@ -177,10 +241,16 @@ pub fn subtract_registers(cpu: &mut Cpu, ins: &Opcode) {
 }
 
 // shift Vx right, if the least significant bit is 1, Vf=1, otherwise 0
+// with the shift quirk Vy is shifted into Vx instead
 pub fn shift_right(cpu: &mut Cpu, ins: &Opcode) {
-    // Vf = least significant bit
-    cpu.register[0xf] = cpu.register[ins.register_x()] & 0x1;
-    cpu.register[ins.register_x()] /= 2; // shift right
+    if cpu.quirks.shift_uses_vy {
+        cpu.register[ins.register_x()] = cpu.register[ins.register_y()];
+    }
+
+    // the flag is written last, so for 8ff6 VF ends up holding the flag
+    let value = cpu.register[ins.register_x()];
+    cpu.register[ins.register_x()] = value / 2; // shift right
+    cpu.register[0xf] = value & 0x1; // Vf = least significant bit
 }
 
 // Vx = Vy - Vx, VF = NOT borrow
@@ -197,10 +267,15 @@ pub fn subtract_negative(cpu: &mut Cpu, ins: &Opcode) {
 }
 
 // shift Vx left, if the most significant bit is 1, Vf=1, otherwise 0
+// with the shift quirk Vy is shifted into Vx instead
 pub fn shift_left(cpu: &mut Cpu, ins: &Opcode) {
-    // Vf = most significant bit
-    cpu.register[0xf] = (cpu.register[ins.register_x()] & 0x80) >> 7;
-    cpu.register[ins.register_x()] <<= 1; // shift left
+    if cpu.quirks.shift_uses_vy {
+        cpu.register[ins.register_x()] = cpu.register[ins.register_y()];
+    }
+
+    let value = cpu.register[ins.register_x()];
+    cpu.register[ins.register_x()] = value << 1; // shift left
+    cpu.register[0xf] = (value & 0x80) >> 7; // Vf = most significant bit
 }
 
 // if Vx != Vy increment pc by 2
@@ -215,15 +290,19 @@ pub fn load_register_i(cpu: &mut Cpu, ins: &Opcode) {
     cpu.register_i = ins.address();
 }
 
-// set pc to V0 + addr
+// set pc to V0 + addr, or Vx + addr with the jump quirk
 pub fn jump_register_0(cpu: &mut Cpu, ins: &Opcode) {
-    cpu.program_counter = (cpu.register[0] as u16 + ins.address()).into();
+    let register = if cpu.quirks.jump_uses_vx {
+        ins.register_x()
+    } else {
+        0
+    };
+    cpu.program_counter = (cpu.register[register] as u16 + ins.address()).into();
 }
 
 // random number between in [0,255], AND with byte, store in Vx
 pub fn random_byte(cpu: &mut Cpu, ins: &Opcode) {
-    let mut rng = rand::thread_rng();
-    let random_number: u8 = rng.gen_range(0..=255);
+    let random_number: u8 = cpu.rng.gen_range(0..=255);
     cpu.register[ins.register_x()] = random_number & ins.byte();
 }
 
@@ -234,27 +313,33 @@ pub fn random_byte(cpu: &mut Cpu, ins: &Opcode) {
 pub fn draw(cpu: &mut Cpu, ins: &Opcode) {
     cpu.register[0xf] = 0;
 
-    let start_x: usize = cpu.register[ins.register_x()] as usize;
-    let start_y: usize = cpu.register[ins.register_y()] as usize;
+    let mut start_x: usize = cpu.register[ins.register_x()] as usize;
+    let mut start_y: usize = cpu.register[ins.register_y()] as usize;
     let height: usize = ins.nibble() as usize;
+    cpu.track_read(cpu.register_i as usize, height);
 
+    // with the clip quirk only the starting position wraps, the parts of the
+    // sprite that go past the edges are not drawn
+    if cpu.quirks.clip_sprites {
+        start_x %= CHIP8_WIDTH as usize;
+        start_y %= CHIP8_HEIGHT as usize;
+    }
+
+    // each sprite row is shifted into place and xored onto the packed display
+    // rows, a collision is any lit pixel under a set bit
     for y in 0..height {
-        let value: u8 = cpu.memory[cpu.register_i as usize + y]; // value from memory
-        for x in 0..8 {
-            if value & (0x80 >> x) != 0 { // the xth bit in the byte == 1
-            }
-            let bit = (value & (0x80 >> x)) >> (7 - x); // the xth bit in the byte
-                                                        //let coord: usize = (((start_y + y) % CHIP8_HEIGHT as usize) * CHIP8_WIDTH as usize
-                                                        //    + ((start_x + x) % CHIP8_WIDTH as usize)) as usize;
-            let coord: usize = (start_y + y) * CHIP8_WIDTH as usize + (start_x + x) as usize;
-            let coord = coord % (CHIP8_WIDTH * CHIP8_HEIGHT) as usize;
-
-            if bit == 1 {
-                if cpu.display[coord] == 1 {
-                    cpu.register[0xf] = 1; // collision
-                }
-                cpu.display[coord] ^= 1;
+        let value: u8 = cpu.memory()[address_from_i(cpu, y)]; // value from memory
+        let collision = if cpu.quirks.clip_sprites {
+            if start_y + y >= CHIP8_HEIGHT as usize {
+                break;
             }
+            framebuffer::xor_clipped(&mut cpu.display, start_x, start_y + y, value)
+        } else {
+            let index = (start_y + y) * CHIP8_WIDTH as usize + start_x;
+            framebuffer::xor_wrapped(&mut cpu.display, index, value)
+        };
+        if collision {
+            cpu.register[0xf] = 1;
         }
     }
 
@@ -263,18 +348,28 @@ pub fn draw(cpu: &mut Cpu, ins: &Opcode) {
 
 // skip next instruction if key Vx is pressed
 pub fn skip_if_key(cpu: &mut Cpu, ins: &Opcode) {
-    if cpu.keypad[cpu.register[ins.register_x()] as usize] {
+    if cpu.keypad[cpu.register[ins.register_x()] as usize % 16] {
         cpu.program_counter += 2;
     }
 }
 
 // skip next instruction if key Vx is not pressed
 pub fn skip_if_not_key(cpu: &mut Cpu, ins: &Opcode) {
-    if !cpu.keypad[cpu.register[ins.register_x()] as usize] {
+    if !cpu.keypad[cpu.register[ins.register_x()] as usize % 16] {
         cpu.program_counter += 2;
     }
 }
 
+// xo-chip: load the 16 byte audio pattern from memory starting at I
+pub fn load_audio_pattern(cpu: &mut Cpu, _ins: &Opcode) {
+    cpu.track_read(cpu.register_i as usize, 16);
+    for i in 0..16 {
+        cpu.audio_pattern[i] = cpu.memory()[address_from_i(cpu, i)];
+    }
+    cpu.audio_pattern_loaded = true;
+    cpu.audio_flag = true;
+}
+
 // set Vx = DT
 pub fn set_register_to_delay_timer(cpu: &mut Cpu, ins: &Opcode) {
     cpu.register[ins.register_x()] = cpu.delay_timer;
@@ -298,7 +393,9 @@ pub fn set_sound_timer(cpu: &mut Cpu, ins: &Opcode) {
 
 // I += Vx
 pub fn add_register_i(cpu: &mut Cpu, ins: &Opcode) {
-    cpu.register_i += cpu.register[ins.register_x()] as u16;
+    cpu.register_i = cpu
+        .register_i
+        .wrapping_add(cpu.register[ins.register_x()] as u16);
 }
 
 // set I to the location of the sprite of Vx in memory
@@ -306,30 +403,54 @@ pub fn set_register_i_to_sprite(cpu: &mut Cpu, ins: &Opcode) {
     cpu.register_i = cpu.register[ins.register_x()] as u16 * 5;
 }
 
+// xo-chip: set the audio pattern playback rate to Vx
+pub fn set_pitch(cpu: &mut Cpu, ins: &Opcode) {
+    cpu.pitch = cpu.register[ins.register_x()];
+    cpu.audio_flag = true;
+}
+
 // store BCD representation of Vx at I->I+2 in memory
 pub fn store_register_x(cpu: &mut Cpu, ins: &Opcode) {
     let value: u32 = cpu.register[ins.register_x()].into();
+    cpu.track_write(cpu.register_i as usize, 3);
 
-    cpu.memory[cpu.register_i as usize] = ((value % 1000) / 100) as u8;
-    cpu.memory[(cpu.register_i + 1) as usize] = ((value % 100) / 10) as u8;
-    cpu.memory[(cpu.register_i + 2) as usize] = (value % 10) as u8;
+    cpu.write_memory(address_from_i(cpu, 0), ((value % 1000) / 100) as u8);
+    cpu.write_memory(address_from_i(cpu, 1), ((value % 100) / 10) as u8);
+    cpu.write_memory(address_from_i(cpu, 2), (value % 10) as u8);
 }
 
 // store registers V0 to Vx in memory, starting at I
 pub fn store_registers(cpu: &mut Cpu, ins: &Opcode) {
+    cpu.track_write(cpu.register_i as usize, ins.register_x() + 1);
     for i in 0..=ins.register_x() {
-        cpu.memory[cpu.register_i as usize + i] = cpu.register[i];
+        cpu.write_memory(address_from_i(cpu, i), cpu.register[i]);
     }
+
+    move_i_past_registers(cpu, ins);
 }
 
 // read V0 to Vx from memory, starting at I
 pub fn read_registers(cpu: &mut Cpu, ins: &Opcode) {
+    cpu.track_read(cpu.register_i as usize, ins.register_x() + 1);
     for i in 0..=ins.register_x() {
-        cpu.register[i] = cpu.memory[cpu.register_i as usize + i];
+        cpu.register[i] = cpu.memory()[address_from_i(cpu, i)];
+    }
+
+    move_i_past_registers(cpu, ins);
+}
+
+// the load_store_moves_i quirk, some interpreters leave I on the last register
+fn move_i_past_registers(cpu: &mut Cpu, ins: &Opcode) {
+    if cpu.quirks.load_store_moves_i {
+        let registers = ins.register_x() as u16 + !cpu.quirks.load_store_moves_i_by_x as u16;
+        cpu.register_i = cpu.register_i.wrapping_add(registers);
     }
 }
 
-pub fn unknown_instruction(_cpu: &mut Cpu, instruction: &Opcode) {
+pub fn unknown_instruction(cpu: &mut Cpu, instruction: &Opcode) {
     let bytes = instruction.bytes();
-    panic!("Unknown instruction: {:02x?}{:02x?}", bytes.0, bytes.1);
+    cpu.fault = Some(format!(
+        "Unknown instruction: {:02x?}{:02x?} at {:#05x}",
+        bytes.0, bytes.1, cpu.program_counter
+    ));
 }
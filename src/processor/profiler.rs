@@ -0,0 +1,300 @@
+use super::disassembler::disassemble;
+use super::symbols::Symbols;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error;
+use std::fs;
+
+const HOT_SPOTS: usize = 50; // addresses listed in the text report
+const MAX_CALL_DEPTH: usize = 16;
+
+// where a program spends its instructions, filled in by execute_instruction
+#[derive(Clone)]
+pub struct Profiler {
+    instructions: u64,
+    address_counts: Vec<u64>, // instructions executed from each address
+    type_counts: HashMap<&'static str, u64>,
+    calls: HashMap<u16, CallCount>, // by subroutine address
+    call_stack: Vec<(u16, u64)>,    // subroutine and the instruction count when it was called
+    frame_instructions: Vec<u32>,
+    current_frame: u32,
+}
+
+#[derive(Clone, Copy, Default)]
+struct CallCount {
+    calls: u64,
+    cycles: u64, // instructions from the call up to and including the return
+}
+
+#[derive(Serialize)]
+struct Report {
+    instructions: u64,
+    frames: FrameSummary,
+    hot_spots: Vec<HotSpot>,
+    instruction_types: Vec<TypeCount>,
+    subroutines: Vec<Subroutine>,
+    instructions_per_frame: Vec<u32>,
+}
+
+#[derive(Serialize)]
+struct FrameSummary {
+    count: usize,
+    min: u32,
+    max: u32,
+    mean: f64,
+}
+
+#[derive(Serialize)]
+struct HotSpot {
+    address: u16,
+    count: u64,
+    opcode: String,
+    instruction: String,
+    label: Option<String>,
+}
+
+#[derive(Serialize)]
+struct TypeCount {
+    instruction: &'static str,
+    count: u64,
+}
+
+#[derive(Serialize)]
+struct Subroutine {
+    address: u16,
+    label: Option<String>,
+    calls: u64,
+    cycles: u64,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            instructions: 0,
+            address_counts: vec![0; 4096],
+            type_counts: HashMap::new(),
+            calls: HashMap::new(),
+            call_stack: Vec::new(),
+            frame_instructions: Vec::new(),
+            current_frame: 0,
+        }
+    }
+
+    // called before each instruction is executed
+    pub fn record(&mut self, address: usize, opcode: u16) {
+        self.instructions += 1;
+        self.current_frame += 1;
+        self.address_counts[address % 4096] += 1;
+        *self
+            .type_counts
+            .entry(instruction_type(opcode))
+            .or_default() += 1;
+
+        if opcode & 0xf000 == 0x2000 {
+            let subroutine = opcode & 0xfff;
+            self.calls.entry(subroutine).or_default().calls += 1;
+            self.call_stack.push((subroutine, self.instructions - 1));
+
+            // programs that leave subroutines with a jump never return
+            if self.call_stack.len() > MAX_CALL_DEPTH {
+                self.call_stack.remove(0);
+            }
+        } else if opcode == 0x00ee {
+            if let Some((subroutine, called_at)) = self.call_stack.pop() {
+                self.calls.entry(subroutine).or_default().cycles += self.instructions - called_at;
+            }
+        }
+    }
+
+    pub fn end_frame(&mut self) {
+        self.frame_instructions.push(self.current_frame);
+        self.current_frame = 0;
+    }
+
+    // json if the file name ends in .json, text otherwise. memory is used to
+    // disassemble the executed addresses
+    pub fn write_report(
+        &self,
+        filename: &str,
+        memory: &[u8; 4096],
+        symbols: &Symbols,
+    ) -> Result<(), Box<dyn error::Error>> {
+        let report = self.report(memory, symbols);
+        let text = if filename.ends_with(".json") {
+            serde_json::to_string_pretty(&report)?
+        } else {
+            report.to_text()
+        };
+
+        fs::write(filename, text)
+            .map_err(|e| format!("could not write '{}': {}", filename, e).into())
+    }
+
+    fn report(&self, memory: &[u8; 4096], symbols: &Symbols) -> Report {
+        let mut hot_spots: Vec<HotSpot> = (0..4096)
+            .filter(|&address| self.address_counts[address] > 0)
+            .map(|address| {
+                let opcode = (memory[address] as u16) << 8 | memory[(address + 1) % 4096] as u16;
+                HotSpot {
+                    address: address as u16,
+                    count: self.address_counts[address],
+                    opcode: format!("{:04x}", opcode),
+                    instruction: disassemble(opcode),
+                    label: symbols.label(address as u16),
+                }
+            })
+            .collect();
+        hot_spots.sort_by(|a, b| b.count.cmp(&a.count).then(a.address.cmp(&b.address)));
+
+        let mut instruction_types: Vec<TypeCount> = self
+            .type_counts
+            .iter()
+            .map(|(&instruction, &count)| TypeCount { instruction, count })
+            .collect();
+        instruction_types
+            .sort_by(|a, b| b.count.cmp(&a.count).then(a.instruction.cmp(b.instruction)));
+
+        let mut subroutines: Vec<Subroutine> = self
+            .calls
+            .iter()
+            .map(|(&address, call)| Subroutine {
+                address,
+                label: symbols.label(address),
+                calls: call.calls,
+                cycles: call.cycles,
+            })
+            .collect();
+        subroutines.sort_by(|a, b| b.cycles.cmp(&a.cycles).then(a.address.cmp(&b.address)));
+
+        let frames = &self.frame_instructions;
+        let total: u64 = frames.iter().map(|&f| f as u64).sum();
+
+        Report {
+            instructions: self.instructions,
+            frames: FrameSummary {
+                count: frames.len(),
+                min: frames.iter().copied().min().unwrap_or(0),
+                max: frames.iter().copied().max().unwrap_or(0),
+                mean: total as f64 / frames.len().max(1) as f64,
+            },
+            hot_spots,
+            instruction_types,
+            subroutines,
+            instructions_per_frame: frames.clone(),
+        }
+    }
+}
+
+impl Report {
+    fn to_text(&self) -> String {
+        let mut text = String::new();
+        let percent = |count: u64| 100.0 * count as f64 / self.instructions.max(1) as f64;
+
+        text += &format!(
+            "{} instructions over {} frames\n",
+            self.instructions, self.frames.count
+        );
+        text += &format!(
+            "instructions per frame: min {}, mean {:.1}, max {}\n",
+            self.frames.min, self.frames.mean, self.frames.max
+        );
+
+        text += "\nhot spots:\n";
+        text += &format!("{:>10} {:>7}  address  opcode  instruction\n", "count", "%");
+        // rows without a label are trimmed so reports diff cleanly
+        for spot in self.hot_spots.iter().take(HOT_SPOTS) {
+            let row = format!(
+                "{:>10} {:>6.2}%  {:#05x}    {}    {:<18} {}",
+                spot.count,
+                percent(spot.count),
+                spot.address,
+                spot.opcode,
+                spot.instruction,
+                spot.label.as_deref().unwrap_or("")
+            );
+            text += row.trim_end();
+            text += "\n";
+        }
+
+        text += "\ninstruction types:\n";
+        for count in &self.instruction_types {
+            text += &format!(
+                "{:>10} {:>6.2}%  {}\n",
+                count.count,
+                percent(count.count),
+                count.instruction
+            );
+        }
+
+        text += "\nsubroutines:\n";
+        text += &format!(
+            "{:>10} {:>10} {:>10}  address\n",
+            "calls", "cycles", "per call"
+        );
+        for subroutine in &self.subroutines {
+            let row = format!(
+                "{:>10} {:>10} {:>10.1}  {:#05x}    {}",
+                subroutine.calls,
+                subroutine.cycles,
+                subroutine.cycles as f64 / subroutine.calls.max(1) as f64,
+                subroutine.address,
+                subroutine.label.as_deref().unwrap_or("")
+            );
+            text += row.trim_end();
+            text += "\n";
+        }
+
+        text
+    }
+}
+
+// the instruction's pattern as written in instruction set references
+fn instruction_type(opcode: u16) -> &'static str {
+    let nibbles = (
+        (opcode >> 12) as u8,
+        ((opcode >> 8) & 0xf) as u8,
+        ((opcode >> 4) & 0xf) as u8,
+        (opcode & 0xf) as u8,
+    );
+
+    match nibbles {
+        (0x0, 0x0, 0xe, 0x0) => "00E0",
+        (0x0, 0x0, 0xe, 0xe) => "00EE",
+        (0x0, _, _, _) => "0nnn",
+        (0x1, _, _, _) => "1nnn",
+        (0x2, _, _, _) => "2nnn",
+        (0x3, _, _, _) => "3xkk",
+        (0x4, _, _, _) => "4xkk",
+        (0x5, _, _, 0x0) => "5xy0",
+        (0x6, _, _, _) => "6xkk",
+        (0x7, _, _, _) => "7xkk",
+        (0x8, _, _, 0x0) => "8xy0",
+        (0x8, _, _, 0x1) => "8xy1",
+        (0x8, _, _, 0x2) => "8xy2",
+        (0x8, _, _, 0x3) => "8xy3",
+        (0x8, _, _, 0x4) => "8xy4",
+        (0x8, _, _, 0x5) => "8xy5",
+        (0x8, _, _, 0x6) => "8xy6",
+        (0x8, _, _, 0x7) => "8xy7",
+        (0x8, _, _, 0xe) => "8xyE",
+        (0x9, _, _, 0x0) => "9xy0",
+        (0xa, _, _, _) => "Annn",
+        (0xb, _, _, _) => "Bnnn",
+        (0xc, _, _, _) => "Cxkk",
+        (0xd, _, _, _) => "Dxyn",
+        (0xe, _, 0x9, 0xe) => "Ex9E",
+        (0xe, _, 0xa, 0x1) => "ExA1",
+        (0xf, 0x0, 0x0, 0x2) => "F002",
+        (0xf, _, 0x0, 0x7) => "Fx07",
+        (0xf, _, 0x0, 0xa) => "Fx0A",
+        (0xf, _, 0x1, 0x5) => "Fx15",
+        (0xf, _, 0x1, 0x8) => "Fx18",
+        (0xf, _, 0x1, 0xe) => "Fx1E",
+        (0xf, _, 0x2, 0x9) => "Fx29",
+        (0xf, _, 0x3, 0x3) => "Fx33",
+        (0xf, _, 0x3, 0xa) => "Fx3A",
+        (0xf, _, 0x5, 0x5) => "Fx55",
+        (0xf, _, 0x6, 0x5) => "Fx65",
+        _ => "unknown",
+    }
+}
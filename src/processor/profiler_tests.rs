@@ -0,0 +1,120 @@
+use super::cpu::*;
+use super::symbols::Symbols;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// tests run in parallel, so every report gets a file of its own
+fn temp_path(test: &str, extension: &str) -> PathBuf {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+    let count = COUNT.fetch_add(1, Ordering::Relaxed);
+    env::temp_dir().join(format!(
+        "chip8-{}-{}-{}.{}",
+        test,
+        std::process::id(),
+        count,
+        extension
+    ))
+}
+
+fn profile(test: &str, program: &[u8], frames: u32, instructions: u32) -> serde_json::Value {
+    let options = CpuOptions {
+        profile: true,
+        ..CpuOptions::default()
+    };
+    let mut cpu = match Cpu::with_options(program, &options) {
+        Ok(x) => x,
+        Err(e) => panic!("{}", e),
+    };
+
+    for _ in 0..frames {
+        cpu.run_frame(&[false; 16], instructions).unwrap();
+    }
+
+    let path = temp_path(test, "json");
+    let filename = path.to_str().unwrap();
+    let profiler = cpu.profiler.as_ref().unwrap();
+    profiler
+        .write_report(filename, cpu.memory(), &Symbols::default())
+        .unwrap();
+
+    let report = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+    fs::remove_file(&path).unwrap();
+    report
+}
+
+#[test]
+fn test_profile_counts() {
+    // call a subroutine once, then loop on a jump
+    let program = [0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x60, 0x01, 0x00, 0xee];
+    let report = profile("profile_counts", &program, 2, 10);
+
+    assert_eq!(report["instructions"], 20);
+    assert_eq!(report["frames"]["count"], 2);
+    assert_eq!(
+        report["instructions_per_frame"],
+        serde_json::json!([10, 10])
+    );
+
+    let hottest = &report["hot_spots"][0];
+    assert_eq!(hottest["address"], 0x202);
+    assert_eq!(hottest["count"], 17);
+    assert_eq!(hottest["instruction"], "JP 0x202");
+
+    let types = &report["instruction_types"][0];
+    assert_eq!(types["instruction"], "1nnn");
+    assert_eq!(types["count"], 17);
+}
+
+#[test]
+fn test_profile_subroutines() {
+    let program = [0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x60, 0x01, 0x00, 0xee];
+    let report = profile("profile_subroutines", &program, 1, 10);
+
+    // the call, the load and the return
+    let subroutine = &report["subroutines"][0];
+    assert_eq!(subroutine["address"], 0x206);
+    assert_eq!(subroutine["calls"], 1);
+    assert_eq!(subroutine["cycles"], 3);
+}
+
+#[test]
+fn test_text_report() {
+    let program = [0x22, 0x06, 0x12, 0x02, 0x00, 0x00, 0x60, 0x01, 0x00, 0xee];
+    let mut cpu = Cpu::with_options(
+        &program,
+        &CpuOptions {
+            profile: true,
+            ..CpuOptions::default()
+        },
+    )
+    .unwrap();
+    cpu.run_frame(&[false; 16], 10).unwrap();
+
+    let path = temp_path("text_report", "txt");
+    let symbols = Symbols::parse("0x206 sub\n").unwrap();
+    let profiler = cpu.profiler.as_ref().unwrap();
+    profiler
+        .write_report(path.to_str().unwrap(), cpu.memory(), &symbols)
+        .unwrap();
+    let text = fs::read_to_string(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    // labelled and unlabelled rows, none ending in spaces
+    assert!(text
+        .lines()
+        .any(|line| line.ends_with("RET                sub+0x2")));
+    assert!(text.lines().any(|line| line.ends_with("JP 0x202")));
+    assert!(text.lines().all(|line| line == line.trim_end()));
+}
+
+#[test]
+fn test_symbol_labels() {
+    let symbols = Symbols::parse("0x200 main\n206 draw # the sprite routine\n").unwrap();
+    assert_eq!(symbols.label(0x200), Some("main".to_string()));
+    assert_eq!(symbols.label(0x206), Some("draw".to_string()));
+    assert_eq!(symbols.label(0x20a), Some("draw+0x4".to_string()));
+    assert_eq!(symbols.label(0x100), None);
+    assert!(Symbols::parse("main 0x200 extra").is_err());
+}
@@ -0,0 +1,152 @@
+use super::cpu::*;
+use super::quirks::Quirks;
+use proptest::prelude::*;
+
+const CYCLES: usize = 500;
+
+// any state a program could leave the cpu in
+#[derive(Debug, Clone)]
+struct State {
+    register: [u8; 16],
+    register_i: u16,
+    program_counter: usize,
+    stack_pointer: usize,
+    delay_timer: u8,
+    sound_timer: u8,
+}
+
+fn state() -> impl Strategy<Value = State> {
+    (
+        any::<[u8; 16]>(),
+        any::<u16>(),
+        0..4096usize,
+        0..16usize,
+        any::<u8>(),
+        any::<u8>(),
+    )
+        .prop_map(
+            |(register, register_i, program_counter, stack_pointer, delay_timer, sound_timer)| {
+                State {
+                    register,
+                    register_i,
+                    program_counter,
+                    stack_pointer,
+                    delay_timer,
+                    sound_timer,
+                }
+            },
+        )
+}
+
+fn quirks() -> impl Strategy<Value = Quirks> {
+    prop::sample::select(&Quirks::PROFILES[..]).prop_map(|name| name.parse().unwrap())
+}
+
+fn cpu(program: &[u8], quirks: Quirks, seed: u64) -> Cpu {
+    let options = CpuOptions {
+        quirks,
+        seed: Some(seed),
+        ..CpuOptions::default()
+    };
+    Cpu::with_options(program, &options).unwrap()
+}
+
+// run until the program faults, checking the cpu after every instruction
+fn run(cpu: &mut Cpu, keys: &[bool; 16]) -> Result<(), TestCaseError> {
+    for _ in 0..CYCLES {
+        if cpu.cycle(keys).is_err() {
+            break;
+        }
+        prop_assert!(cpu.program_counter < 4096);
+        prop_assert!(cpu.stack_pointer < 16);
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn test_random_programs(
+        program in prop::collection::vec(any::<u8>(), 0..=3584),
+        quirks in quirks(),
+        keys in any::<[bool; 16]>(),
+        seed in any::<u64>(),
+    ) {
+        run(&mut cpu(&program, quirks, seed), &keys)?;
+    }
+
+    #[test]
+    fn test_random_states(
+        memory in prop::collection::vec(any::<u8>(), 4096 - 0x200),
+        state in state(),
+        quirks in quirks(),
+        keys in any::<[bool; 16]>(),
+    ) {
+        let mut cpu = cpu(&memory, quirks, 0);
+        cpu.register = state.register;
+        cpu.register_i = state.register_i;
+        cpu.program_counter = state.program_counter;
+        cpu.stack_pointer = state.stack_pointer;
+        cpu.delay_timer = state.delay_timer;
+        cpu.sound_timer = state.sound_timer;
+
+        run(&mut cpu, &keys)?;
+    }
+
+    #[test]
+    fn test_deterministic(
+        program in prop::collection::vec(any::<u8>(), 0..=512),
+        quirks in quirks(),
+        seed in any::<u64>(),
+    ) {
+        let mut first = cpu(&program, quirks, seed);
+        let mut second = cpu(&program, quirks, seed);
+
+        for _ in 0..CYCLES / 10 {
+            let a = first.run_frame(&[false; 16], 10).is_ok();
+            let b = second.run_frame(&[false; 16], 10).is_ok();
+            prop_assert_eq!(a, b);
+            if !a {
+                break;
+            }
+        }
+
+        prop_assert_eq!(first.program_counter, second.program_counter);
+        prop_assert_eq!(first.register, second.register);
+        prop_assert_eq!(first.register_i, second.register_i);
+        prop_assert_eq!(&first.memory()[..], &second.memory()[..]);
+        prop_assert_eq!(first.display, second.display);
+    }
+
+    #[test]
+    fn test_frame_matches_cycles(
+        program in prop::collection::vec(any::<u8>(), 0..=512),
+        quirks in quirks(),
+        keys in any::<[bool; 16]>(),
+        seed in any::<u64>(),
+    ) {
+        let mut frames = cpu(&program, quirks, seed);
+        let mut cycles = cpu(&program, quirks, seed);
+
+        for _ in 0..CYCLES / 10 {
+            let frame = frames.run_frame(&keys, 10);
+            let mut cycle = Ok(());
+            for _ in 0..10 {
+                if let Err(e) = cycles.cycle(&keys) {
+                    cycle = Err(e);
+                    break;
+                }
+            }
+            prop_assert_eq!(frame.is_ok(), cycle.is_ok());
+            if frame.is_err() {
+                break;
+            }
+            cycles.tick_timers();
+        }
+
+        prop_assert_eq!(frames.program_counter, cycles.program_counter);
+        prop_assert_eq!(frames.register, cycles.register);
+        prop_assert_eq!(frames.cycles, cycles.cycles);
+        prop_assert_eq!(&frames.memory()[..], &cycles.memory()[..]);
+        prop_assert_eq!(frames.display, cycles.display);
+    }
+}
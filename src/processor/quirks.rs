@@ -0,0 +1,114 @@
+use std::fmt;
+use std::str::FromStr;
+
+// behaviours that differ between chip8 interpreters, the default is the
+// behaviour this emulator has always had
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Quirks {
+    pub shift_uses_vy: bool, // 8xy6/8xye shift Vy into Vx instead of shifting Vx
+    pub load_store_moves_i: bool, // fx55/fx65 leave I pointing past the last register
+    pub load_store_moves_i_by_x: bool, // with load_store_moves_i, I moves by x instead of x + 1
+    pub jump_uses_vx: bool,  // bnnn jumps to Vx + nnn instead of V0 + nnn
+    pub logic_resets_vf: bool, // 8xy1/8xy2/8xy3 set VF to 0
+    pub clip_sprites: bool,  // sprites are cut off at the screen edges instead of wrapping
+}
+
+impl Quirks {
+    pub const PROFILES: [&'static str; 4] = ["default", "chip8", "schip", "xochip"];
+
+    // the original cosmac vip interpreter
+    pub fn chip8() -> Self {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_moves_i: true,
+            load_store_moves_i_by_x: false,
+            jump_uses_vx: false,
+            logic_resets_vf: true,
+            clip_sprites: true,
+        }
+    }
+
+    pub fn schip() -> Self {
+        Quirks {
+            shift_uses_vy: false,
+            load_store_moves_i: false,
+            load_store_moves_i_by_x: false,
+            jump_uses_vx: true,
+            logic_resets_vf: false,
+            clip_sprites: true,
+        }
+    }
+
+    pub fn xochip() -> Self {
+        Quirks {
+            shift_uses_vy: true,
+            load_store_moves_i: true,
+            load_store_moves_i_by_x: false,
+            jump_uses_vx: false,
+            logic_resets_vf: false,
+            clip_sprites: false,
+        }
+    }
+}
+
+// a profile name, or a comma separated list of the quirks that are enabled
+impl FromStr for Quirks {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "default" => return Ok(Quirks::default()),
+            "chip8" => return Ok(Quirks::chip8()),
+            "schip" => return Ok(Quirks::schip()),
+            "xochip" => return Ok(Quirks::xochip()),
+            _ => {}
+        }
+
+        let mut quirks = Quirks::default();
+        for name in s.split(',') {
+            match name.trim() {
+                "shift_uses_vy" => quirks.shift_uses_vy = true,
+                "load_store_moves_i" => quirks.load_store_moves_i = true,
+                "load_store_moves_i_by_x" => quirks.load_store_moves_i_by_x = true,
+                "jump_uses_vx" => quirks.jump_uses_vx = true,
+                "logic_resets_vf" => quirks.logic_resets_vf = true,
+                "clip_sprites" => quirks.clip_sprites = true,
+                _ => {
+                    return Err(format!(
+                        "unknown quirk profile '{}', expected default, chip8, schip, xochip or a list of quirks",
+                        s
+                    ))
+                }
+            }
+        }
+
+        Ok(quirks)
+    }
+}
+
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let profiles = [
+            ("default", Quirks::default()),
+            ("chip8", Quirks::chip8()),
+            ("schip", Quirks::schip()),
+            ("xochip", Quirks::xochip()),
+        ];
+
+        if let Some((name, _)) = profiles.iter().find(|(_, quirks)| quirks == self) {
+            return write!(f, "{}", name);
+        }
+
+        let flags = [
+            ("shift_uses_vy", self.shift_uses_vy),
+            ("load_store_moves_i", self.load_store_moves_i),
+            ("load_store_moves_i_by_x", self.load_store_moves_i_by_x),
+            ("jump_uses_vx", self.jump_uses_vx),
+            ("logic_resets_vf", self.logic_resets_vf),
+            ("clip_sprites", self.clip_sprites),
+        ];
+        let enabled: Vec<&str> = flags.iter().filter(|f| f.1).map(|f| f.0).collect();
+
+        write!(f, "{}", enabled.join(","))
+    }
+}
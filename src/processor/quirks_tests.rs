@@ -0,0 +1,41 @@
+use super::quirks::*;
+
+#[test]
+fn test_profiles_from_str() {
+    assert_eq!("default".parse(), Ok(Quirks::default()));
+    assert_eq!("chip8".parse(), Ok(Quirks::chip8()));
+    assert_eq!("schip".parse(), Ok(Quirks::schip()));
+    assert_eq!("xochip".parse(), Ok(Quirks::xochip()));
+
+    for name in Quirks::PROFILES {
+        let quirks: Quirks = name.parse().unwrap();
+        assert_eq!(quirks.to_string(), name);
+    }
+}
+
+#[test]
+fn test_quirk_list_from_str() {
+    let quirks: Quirks = "shift_uses_vy, jump_uses_vx".parse().unwrap();
+    assert_eq!(
+        quirks,
+        Quirks {
+            shift_uses_vy: true,
+            jump_uses_vx: true,
+            ..Quirks::default()
+        }
+    );
+    assert_eq!(quirks.to_string(), "shift_uses_vy,jump_uses_vx");
+    assert_eq!(quirks.to_string().parse(), Ok(quirks));
+
+    let quirks: Quirks = "load_store_moves_i,load_store_moves_i_by_x"
+        .parse()
+        .unwrap();
+    assert!(quirks.load_store_moves_i && quirks.load_store_moves_i_by_x);
+}
+
+#[test]
+fn test_unknown_quirk() {
+    assert!("cosmac".parse::<Quirks>().is_err());
+    assert!("shift_uses_vy,wrap".parse::<Quirks>().is_err());
+    assert!("Chip8".parse::<Quirks>().is_err());
+}
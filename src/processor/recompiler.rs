@@ -0,0 +1,267 @@
+use super::cpu::CpuOptions;
+use super::disassembler::disassemble;
+use std::collections::BTreeSet;
+
+// bytes of the program per line of the generated array
+const BYTES_PER_LINE: usize = 16;
+
+// window pixels per chip8 pixel in the generated program
+const SCALE: u32 = 10;
+
+// rust source for a standalone program, built against the chip8 library. the
+// instructions that can be reached from the start address are translated
+// into a match on the program counter, code that was written over since and
+// computed jumps go to the interpreter. the quirks and instruction rate are
+// fixed when the program is recompiled
+pub fn recompile(
+    program: &[u8],
+    options: &CpuOptions,
+    instructions_per_frame: u32,
+    name: &str,
+) -> String {
+    let start = options.start_address;
+    let end = start + program.len();
+    let opcode_at = |address: usize| {
+        (program[address - start] as u16) << 8 | program[address + 1 - start] as u16
+    };
+
+    let mut arms = String::new();
+    for address in reachable(start, end, opcode_at) {
+        let opcode = opcode_at(address);
+        if let Some(code) = translate(opcode) {
+            arms += &format!("        // {}\n", disassemble(opcode));
+            arms += &format!("        {:#05x} => {{\n", address);
+            arms += "            cpu.cycles += 1;\n";
+            for line in code.lines() {
+                arms += &format!("            {}\n", line);
+            }
+            arms += "        }\n";
+        }
+    }
+
+    let mut text = format!(
+        "// recompiled from {} by chip8 recompile, regenerate it instead of editing.\n",
+        name
+    );
+    text += "// build it as a binary of a crate that depends on chip8, src/main.rs for\n";
+    text += "// example\n\n";
+    text += "use chip8::host;\n";
+    if arms.contains("operations::") {
+        text += "use chip8::processor::operations::{self, Opcode};\n";
+    }
+    text += "use chip8::processor::{Cpu, CpuOptions, FrameOutput};\n";
+    text += "use std::error;\n";
+    text += "use std::process;\n\n";
+
+    text += &format!("pub const START_ADDRESS: usize = {:#05x};\n", start);
+    text += &format!(
+        "pub const QUIRKS: &str = {:?};\n",
+        options.quirks.to_string()
+    );
+    text += &format!(
+        "pub const INSTRUCTIONS_PER_FRAME: u32 = {};\n\n",
+        instructions_per_frame
+    );
+
+    text += &format!("pub const PROGRAM: [u8; {}] = [\n", program.len());
+    for line in program.chunks(BYTES_PER_LINE) {
+        let bytes: Vec<String> = line.iter().map(|b| format!("{:#04x}", b)).collect();
+        text += &format!("    {},\n", bytes.join(", "));
+    }
+    text += "];\n\n";
+
+    text += "fn main() {\n";
+    text += "    if let Err(e) = run() {\n";
+    text += "        eprintln!(\"{}\", e);\n";
+    text += "        process::exit(1);\n";
+    text += "    }\n";
+    text += "}\n\n";
+
+    text += "// run the program in a window until it is closed\n";
+    text += "pub fn run() -> Result<(), Box<dyn error::Error>> {\n";
+    text += "    let options = CpuOptions {\n";
+    text += "        quirks: QUIRKS.parse()?,\n";
+    text += "        start_address: START_ADDRESS,\n";
+    text += "        ..CpuOptions::default()\n";
+    text += "    };\n";
+    text += "    let mut cpu = Cpu::with_options(&PROGRAM, &options)?;\n";
+    text += &format!(
+        "    let (mut display, mut input, mut timer) = host::open_sdl({:?}, {})?;\n",
+        name, SCALE
+    );
+    text += "    host::run(&mut cpu, &mut display, &mut input, &mut timer, run_frame)\n";
+    text += "}\n\n";
+
+    text += "// one 60hz frame. the profiler, coverage and trace only see instructions\n";
+    text += "// run by the interpreter, so the whole frame is interpreted when one is on\n";
+    text += "pub fn run_frame(cpu: &mut Cpu, keys: &[bool; 16]) -> Result<FrameOutput, Box<dyn error::Error>> {\n";
+    text += "    if cpu.instrumented() {\n";
+    text += "        cpu.run_frame(keys, INSTRUCTIONS_PER_FRAME)\n";
+    text += "    } else {\n";
+    text += "        cpu.run_frame_with(keys, INSTRUCTIONS_PER_FRAME, execute)\n";
+    text += "    }\n";
+    text += "}\n\n";
+
+    text += "// run the instruction at the program counter, for Cpu::run_frame_with\n";
+    text += "pub fn execute(cpu: &mut Cpu) {\n";
+    text += "    let pc = cpu.program_counter;\n";
+    text += "    if cpu.rewritten(pc) {\n";
+    text += "        cpu.execute();\n";
+    text += "        return;\n";
+    text += "    }\n";
+    text += "    match pc {\n";
+    text += &arms;
+    text += "        _ => cpu.execute(),\n";
+    text += "    }\n";
+    text += "}\n";
+    text
+}
+
+// addresses of the instructions in the program that control can flow to from
+// the start, without running it. computed jumps aren't followed
+pub fn reachable<F: Fn(usize) -> u16>(start: usize, end: usize, opcode_at: F) -> BTreeSet<usize> {
+    let mut found = BTreeSet::new();
+    let mut pending = vec![start];
+
+    while let Some(address) = pending.pop() {
+        // both bytes of the instruction have to be in the program
+        if address < start || address + 2 > end || !found.insert(address) {
+            continue;
+        }
+
+        let opcode = opcode_at(address);
+        let next = address + 2;
+        let target = (opcode & 0xfff) as usize;
+        match (opcode >> 12, opcode & 0xf) {
+            (0x0, 0xe) => {}
+            (0x1, _) => pending.push(target),
+            (0x2, _) => pending.extend([target, next]),
+            (0x3 | 0x4 | 0x5 | 0x9 | 0xe, _) => pending.extend([next, next + 2]),
+            (0xb, _) => {}
+            _ => pending.push(next),
+        }
+    }
+
+    found
+}
+
+// statements doing what the interpreter's operation for the opcode does,
+// decoded the way operation_map decodes it. the program counter is moved on
+// by 2 afterwards, as in the interpreter. None for computed jumps and
+// unknown instructions, which are left to the interpreter
+fn translate(opcode: u16) -> Option<String> {
+    let x = (opcode >> 8) & 0xf;
+    let y = (opcode >> 4) & 0xf;
+    let byte = opcode & 0xff;
+    let address = opcode & 0xfff;
+    let vx = format!("cpu.register[{:#x}]", x);
+    let vy = format!("cpu.register[{:#x}]", y);
+
+    let code = match (opcode >> 12, opcode & 0xf) {
+        (0x0, 0x0) => "cpu.display = [0; 32];\ncpu.redraw_flag = true;".to_string(),
+        (0x0, 0xe) => call("return_from_subroutine", opcode),
+        (0x1, _) => format!(
+            "cpu.program_counter = {:#05x};",
+            (address as usize + 4094) % 4096
+        ),
+        (0x2, _) => call("call_subroutine", opcode),
+        (0x3, _) => skip(&format!("{} == {:#04x}", vx, byte)),
+        (0x4, _) => skip(&format!("{} != {:#04x}", vx, byte)),
+        // comparing a register with itself always skips, or never does
+        (0x5, _) if x == y => "cpu.program_counter += 2;".to_string(),
+        (0x9, _) if x == y => String::new(),
+        (0x5, _) => skip(&format!("{} == {}", vx, vy)),
+        (0x9, _) => skip(&format!("{} != {}", vx, vy)),
+        (0x6, _) => format!("{} = {:#04x};", vx, byte),
+        (0x7, _) => add(&vx, &format!("{:#04x}", byte)),
+        (0x8, 0x0) if x == y => String::new(),
+        (0x8, 0x0) => format!("{} = {};", vx, vy),
+        (0x8, 0x1) => logic(&vx, "|=", &vy, x == y),
+        (0x8, 0x2) => logic(&vx, "&=", &vy, x == y),
+        (0x8, 0x3) => logic(&vx, "^=", &vy, x == y),
+        (0x8, 0x4) => add(&vx, &vy),
+        (0x8, 0x5) => subtract(&vx, &vx, &vy),
+        (0x8, 0x6) => shift(&vx, &vy, x == y, "value >> 1", "value & 0x1"),
+        (0x8, 0x7) => subtract(&vx, &vy, &vx),
+        (0x8, 0xe) => shift(&vx, &vy, x == y, "value << 1", "value >> 7"),
+        (0xa, _) => format!("cpu.register_i = {:#05x};", address),
+        (0xc, _) => call("random_byte", opcode),
+        (0xd, _) => call("draw", opcode),
+        (0xe, 0xe) => skip(&format!("cpu.keypad[{} as usize % 16]", vx)),
+        (0xe, 0x1) => skip(&format!("!cpu.keypad[{} as usize % 16]", vx)),
+        (0xf, _) => match byte {
+            0x02 => call("load_audio_pattern", opcode),
+            0x07 => format!("{} = cpu.delay_timer;", vx),
+            0x0a => format!(
+                "cpu.keypad_waiting = true;\ncpu.keypad_waiting_register = {:#x};",
+                x
+            ),
+            0x15 => format!("cpu.delay_timer = {};", vx),
+            0x18 => format!("cpu.sound_timer = {};", vx),
+            0x1e => format!(
+                "cpu.register_i = cpu.register_i.wrapping_add({} as u16);",
+                vx
+            ),
+            0x29 => format!("cpu.register_i = {} as u16 * 5;", vx),
+            0x33 => call("store_register_x", opcode),
+            0x3a => format!("cpu.pitch = {};\ncpu.audio_flag = true;", vx),
+            0x55 => call("store_registers", opcode),
+            0x65 => call("read_registers", opcode),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(code)
+}
+
+// the interpreter's operation, without fetching and decoding the opcode
+fn call(operation: &str, opcode: u16) -> String {
+    format!(
+        "operations::{}(cpu, &Opcode::from({:#06x}));",
+        operation, opcode
+    )
+}
+
+fn skip(condition: &str) -> String {
+    format!("if {} {{\n    cpu.program_counter += 2;\n}}", condition)
+}
+
+// the flag is written before the result, so with VF as the target the result wins
+fn add(target: &str, operand: &str) -> String {
+    format!(
+        "let (value, carry) = {}.overflowing_add({});\ncpu.register[0xf] = carry as u8;\n{} = value;",
+        target, operand, target
+    )
+}
+
+fn subtract(target: &str, left: &str, right: &str) -> String {
+    format!(
+        "let (value, borrow) = {}.overflowing_sub({});\ncpu.register[0xf] = !borrow as u8;\n{} = value;",
+        left, right, target
+    )
+}
+
+// or and and of a register with itself leave it as it is, xor clears it
+fn logic(target: &str, operator: &str, operand: &str, same: bool) -> String {
+    let operation = match (same, operator) {
+        (false, _) => format!("{} {} {};\n", target, operator, operand),
+        (true, "^=") => format!("{} = 0;\n", target),
+        (true, _) => String::new(),
+    };
+    operation + "if cpu.quirks.logic_resets_vf {\n    cpu.register[0xf] = 0;\n}"
+}
+
+// the flag is written after the result, as in the interpreter
+fn shift(target: &str, source: &str, same: bool, result: &str, flag: &str) -> String {
+    let mut code = String::new();
+    if !same {
+        code += &format!(
+            "if cpu.quirks.shift_uses_vy {{\n    {} = {};\n}}\n",
+            target, source
+        );
+    }
+    code + &format!(
+        "let value = {};\n{} = {};\ncpu.register[0xf] = {};",
+        target, target, result, flag
+    )
+}
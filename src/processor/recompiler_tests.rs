@@ -0,0 +1,96 @@
+use super::recompiler::{reachable, recompile};
+use super::{CpuOptions, Quirks};
+
+fn opcodes(program: &[u16]) -> Vec<u8> {
+    program.iter().flat_map(|i| i.to_be_bytes()).collect()
+}
+
+fn reachable_in(program: &[u16]) -> Vec<usize> {
+    let bytes = opcodes(program);
+    let opcode_at = |a: usize| (bytes[a - 0x200] as u16) << 8 | bytes[a + 1 - 0x200] as u16;
+    reachable(0x200, 0x200 + bytes.len(), opcode_at)
+        .into_iter()
+        .collect()
+}
+
+#[test]
+fn test_reachable() {
+    let program = [
+        0x2208, // CALL 0x208
+        0x3001, // SE V0, 0x01
+        0x1200, // JP 0x200
+        0xb20c, // JP V0, 0x20c
+        0x7001, // ADD V0, 0x01
+        0x00ee, // RET
+        0xffff, // data
+    ];
+
+    // the skip's instruction and the one after, not past RET or the computed jump
+    assert_eq!(
+        reachable_in(&program),
+        [0x200, 0x202, 0x204, 0x206, 0x208, 0x20a]
+    );
+}
+
+#[test]
+fn test_reachable_stays_in_program() {
+    // jumps out of the program and an instruction cut off at the end
+    assert_eq!(reachable_in(&[0x6001, 0x1300]), [0x200, 0x202]);
+    let bytes = opcodes(&[0x6001]);
+    let cut = &bytes[..1];
+    assert!(reachable(0x200, 0x201, |a| cut[a - 0x200] as u16).is_empty());
+}
+
+#[test]
+fn test_recompile() {
+    let options = CpuOptions {
+        quirks: Quirks::chip8(),
+        ..CpuOptions::default()
+    };
+    let program = opcodes(&[0x6005, 0xb300, 0x8556, 0x1202]);
+    let source = recompile(&program, &options, 15, "test.ch8");
+
+    assert!(source.contains(
+        "pub const PROGRAM: [u8; 8] = [\n    0x60, 0x05, 0xb3, 0x00, 0x85, 0x56, 0x12, 0x02,\n];"
+    ));
+    assert!(source.contains("pub const QUIRKS: &str = \"chip8\";"));
+    assert!(source.contains("pub const INSTRUCTIONS_PER_FRAME: u32 = 15;"));
+    assert!(source.contains("host::open_sdl(\"test.ch8\", 10)?;"));
+    assert!(source.contains("        // LD V0, 0x05\n        0x200 => {"));
+    assert!(source.contains("cpu.register[0x0] = 0x05;"));
+    // computed jumps are left to the interpreter
+    assert!(!source.contains("0x202 =>"));
+    assert!(source.contains("_ => cpu.execute(),"));
+}
+
+#[test]
+fn test_recompile_operations() {
+    let source = recompile(
+        &opcodes(&[0x2206, 0xd015, 0x1202, 0x00ee]),
+        &CpuOptions::default(),
+        10,
+        "test.ch8",
+    );
+
+    // calls, draws and returns call the interpreter's operations directly
+    assert!(source.contains("use chip8::processor::operations::{self, Opcode};"));
+    assert!(source.contains("operations::call_subroutine(cpu, &Opcode::from(0x2206));"));
+    assert!(source.contains("operations::draw(cpu, &Opcode::from(0xd015));"));
+    assert!(source.contains("operations::return_from_subroutine(cpu, &Opcode::from(0x00ee));"));
+
+    // without them the import would be unused
+    let source = recompile(&opcodes(&[0x1200]), &CpuOptions::default(), 10, "test.ch8");
+    assert!(!source.contains("operations"));
+}
+
+#[test]
+fn test_recompile_same_register() {
+    let program = opcodes(&[0x5110, 0x9220, 0x8330, 0x8443, 0x8556, 0x1200]);
+    let source = recompile(&program, &CpuOptions::default(), 10, "test.ch8");
+
+    // no comparisons or assignments of a register with itself
+    assert!(!source.contains("cpu.register[0x1] == cpu.register[0x1]"));
+    assert!(!source.contains("cpu.register[0x3] = cpu.register[0x3]"));
+    assert!(!source.contains("cpu.register[0x5] = cpu.register[0x5]"));
+    assert!(source.contains("cpu.register[0x4] = 0;"));
+}
@@ -0,0 +1,56 @@
+use std::collections::BTreeMap;
+use std::error;
+use std::fs;
+use std::path::Path;
+
+// labels for addresses, from a symbol file with an "<address> <label>" pair on
+// each line. addresses are hex, with or without 0x, and # starts a comment
+#[derive(Default)]
+pub struct Symbols {
+    labels: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn error::Error>> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("could not read '{}': {}", path.display(), e))?;
+        Symbols::parse(&text).map_err(|e| format!("'{}': {}", path.display(), e).into())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut labels = BTreeMap::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let mut fields = line.split_whitespace();
+            let (address, label) = match (fields.next(), fields.next(), fields.next()) {
+                (Some(address), Some(label), None) => (address, label),
+                _ => return Err(format!("line {}: expected '<address> <label>'", number + 1)),
+            };
+
+            let digits = address.trim_start_matches("0x");
+            let address = u16::from_str_radix(digits, 16)
+                .ok()
+                .filter(|&a| a < 4096)
+                .ok_or(format!("line {}: bad address '{}'", number + 1, address))?;
+
+            labels.insert(address, label.to_string());
+        }
+
+        Ok(Symbols { labels })
+    }
+
+    // the closest label at or before an address, with the offset from it
+    pub fn label(&self, address: u16) -> Option<String> {
+        let (&start, name) = self.labels.range(..=address).next_back()?;
+        if start == address {
+            Some(name.clone())
+        } else {
+            Some(format!("{}+{:#x}", name, address - start))
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use super::disassembler::disassemble;
+use super::Cpu;
+use std::error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+// which instructions are traced, and what goes in each line
+#[derive(Clone, Default)]
+pub struct TraceOptions {
+    pub ranges: Vec<(usize, usize)>, // inclusive address ranges, all addresses if empty
+    pub start: u64,                  // first cycle traced
+    pub stop: Option<u64>,           // last cycle traced
+    pub memory: bool,                // add a checksum of memory to each line
+}
+
+// trace lines for the instructions executed since they were last taken, kept
+// by the cpu and written out by whoever runs it
+#[derive(Clone)]
+pub struct Tracer {
+    options: TraceOptions,
+    lines: Vec<String>,
+}
+
+// a trace being written to a file
+pub struct TraceFile {
+    writer: BufWriter<File>,
+}
+
+impl Tracer {
+    pub fn new(options: &TraceOptions) -> Self {
+        Tracer {
+            options: options.clone(),
+            lines: Vec::new(),
+        }
+    }
+
+    // called before each instruction is executed
+    pub fn record(&mut self, cpu: &Cpu) {
+        let options = &self.options;
+        let pc = cpu.program_counter;
+
+        let in_range = options.ranges.is_empty()
+            || options
+                .ranges
+                .iter()
+                .any(|&(start, end)| (start..=end).contains(&pc));
+        let in_cycles = cpu.cycles >= options.start && options.stop.is_none_or(|s| cpu.cycles <= s);
+
+        if in_range && in_cycles {
+            self.lines.push(trace_line(cpu, options.memory));
+        }
+    }
+
+    pub fn take_lines(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.lines)
+    }
+}
+
+impl TraceFile {
+    // start tracing a cpu into a file
+    pub fn start(
+        filename: &str,
+        cpu: &mut Cpu,
+        options: &TraceOptions,
+    ) -> Result<Self, Box<dyn error::Error>> {
+        let file = File::create(filename)
+            .map_err(|e| format!("could not create '{}': {}", filename, e))?;
+        let mut writer = BufWriter::new(file);
+        writeln!(writer, "{}", trace_header(options.memory))?;
+
+        cpu.tracer = Some(Tracer::new(options));
+        Ok(TraceFile { writer })
+    }
+
+    // write the instructions traced since the last call
+    pub fn write(&mut self, cpu: &mut Cpu) -> Result<(), Box<dyn error::Error>> {
+        if let Some(tracer) = &mut cpu.tracer {
+            for line in tracer.take_lines() {
+                writeln!(self.writer, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), Box<dyn error::Error>> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+// the column names, lined up with trace_line
+pub fn trace_header(memory: bool) -> String {
+    let registers: Vec<String> = (0..16).map(|i| format!("v{:x}", i)).collect();
+    let mut header = format!(
+        "#{:>9} {:>3} {:>4} {} {:>4} {:>2} {:>2} {:>2}",
+        "cycle",
+        "pc",
+        "op",
+        registers.join(" "),
+        "i",
+        "sp",
+        "dt",
+        "st"
+    );
+    if memory {
+        header += &format!(" {:>8}", "memory");
+    }
+    header + " instruction"
+}
+
+// the state before an instruction runs, in fixed width columns that stay the
+// same between versions so traces can be compared with diff. the mnemonic is
+// last since it is the only column with spaces
+pub fn trace_line(cpu: &Cpu, memory: bool) -> String {
+    let pc = cpu.program_counter;
+    let opcode = cpu.opcode_at(pc);
+    let registers: Vec<String> = cpu.register.iter().map(|v| format!("{:02x}", v)).collect();
+
+    let mut line = format!(
+        "{:>10} {:03x} {:04x} {} {:04x} {:02x} {:02x} {:02x}",
+        cpu.cycles,
+        pc,
+        opcode,
+        registers.join(" "),
+        cpu.register_i,
+        cpu.stack_pointer,
+        cpu.delay_timer,
+        cpu.sound_timer
+    );
+    if memory {
+        line += &format!(" {:08x}", checksum(cpu.memory()));
+    }
+    line + " " + &disassemble(opcode)
+}
+
+// fnv-1a, cheap enough to run for every instruction
+fn checksum(memory: &[u8]) -> u32 {
+    memory.iter().fold(0x811c9dc5, |hash, &byte| {
+        (hash ^ byte as u32).wrapping_mul(0x01000193)
+    })
+}
+
+// a column that differs between an expected and an actual trace line
+#[derive(Debug, PartialEq)]
+pub struct Difference {
+    pub column: String,
+    pub expected: String,
+    pub actual: String,
+}
+
+// compare the state columns of two trace lines. the mnemonic is left out so
+// traces from other emulators only have to agree on the state
+pub fn compare_lines(expected: &str, actual: &str, memory: bool) -> Vec<Difference> {
+    let header = trace_header(memory);
+    let columns = header
+        .split_whitespace()
+        .skip(1)
+        .take_while(|&c| c != "instruction");
+    let mut expected = expected.split_whitespace();
+    let mut actual = actual.split_whitespace();
+
+    columns
+        .filter_map(|column| {
+            let expected = expected.next().unwrap_or("-");
+            let actual = actual.next().unwrap_or("-");
+            // numbers are compared by value so leading zeros and case don't matter
+            let radix = if column == "cycle" { 10 } else { 16 };
+            let same = match (
+                u64::from_str_radix(expected, radix),
+                u64::from_str_radix(actual, radix),
+            ) {
+                (Ok(a), Ok(b)) => a == b,
+                _ => expected == actual,
+            };
+            if same {
+                return None;
+            }
+            Some(Difference {
+                column: column.to_string(),
+                expected: expected.to_string(),
+                actual: actual.to_string(),
+            })
+        })
+        .collect()
+}
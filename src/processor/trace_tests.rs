@@ -0,0 +1,96 @@
+use super::cpu::*;
+use super::trace::{compare_lines, trace_header, TraceOptions, Tracer};
+
+// V0 = 5, I = 0x300, then loop on a jump
+const PROGRAM: [u8; 6] = [0x60, 0x05, 0xa3, 0x00, 0x12, 0x04];
+
+fn trace(options: &TraceOptions, instructions: u32) -> Vec<String> {
+    let mut cpu = match Cpu::new(&PROGRAM.to_vec()) {
+        Ok(x) => x,
+        Err(e) => panic!("{}", e),
+    };
+    cpu.tracer = Some(Tracer::new(options));
+    cpu.run_frame(&[false; 16], instructions).unwrap();
+    cpu.tracer.as_mut().unwrap().take_lines()
+}
+
+#[test]
+fn test_trace_lines() {
+    let lines = trace(&TraceOptions::default(), 3);
+    let zeros = "00 ".repeat(15);
+
+    assert_eq!(lines.len(), 3);
+    assert_eq!(
+        lines[0],
+        format!("         0 200 6005 {}00 0000 00 00 00 LD V0, 0x05", zeros)
+    );
+    assert_eq!(
+        lines[2],
+        format!("         2 204 1204 05 {}0300 00 00 00 JP 0x204", zeros)
+    );
+    assert_eq!(
+        trace_header(false).len(),
+        lines[2].len() - " JP 0x204".len() + " instruction".len()
+    );
+}
+
+#[test]
+fn test_trace_filters() {
+    let options = TraceOptions {
+        ranges: vec![(0x204, 0x204)],
+        start: 3,
+        stop: Some(5),
+        ..TraceOptions::default()
+    };
+    let lines = trace(&options, 10);
+
+    let cycles: Vec<&str> = lines
+        .iter()
+        .map(|l| l.split_whitespace().next().unwrap())
+        .collect();
+    assert_eq!(cycles, ["3", "4", "5"]);
+}
+
+#[test]
+fn test_trace_memory() {
+    let options = TraceOptions {
+        memory: true,
+        ..TraceOptions::default()
+    };
+    let lines = trace(&options, 1);
+    let columns: Vec<&str> = lines[0].split_whitespace().collect();
+
+    // cycle, pc, opcode, 16 registers, i, sp, dt, st, then the checksum. the
+    // header starts with a # of its own
+    assert_eq!(columns[23].len(), 8);
+    let header = trace_header(true);
+    assert_eq!(header.split_whitespace().nth(24), Some("memory"));
+}
+
+#[test]
+fn test_compare_lines() {
+    let lines = trace(&TraceOptions::default(), 3);
+    assert!(compare_lines(&lines[1], &lines[1], false).is_empty());
+
+    // another emulator's mnemonics and leading zeros don't count
+    let other = lines[1]
+        .replace("LD I, 0x300", "MVI 300")
+        .replacen(" 0300 ", " 300 ", 1);
+    assert!(compare_lines(&other, &lines[1], false).is_empty());
+
+    let differences = compare_lines(&lines[1], &lines[2], false);
+    let columns: Vec<&str> = differences.iter().map(|d| d.column.as_str()).collect();
+    assert_eq!(columns, ["cycle", "pc", "op", "i"]);
+    assert_eq!(differences[1].expected, "202");
+    assert_eq!(differences[1].actual, "204");
+}
+
+#[test]
+fn test_compare_short_line() {
+    let lines = trace(&TraceOptions::default(), 1);
+    let differences = compare_lines("0 200 6005", &lines[0], false);
+
+    assert_eq!(differences.len(), 20);
+    assert_eq!(differences[0].column, "v0");
+    assert_eq!(differences[0].expected, "-");
+}
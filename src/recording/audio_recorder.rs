@@ -0,0 +1,49 @@
+use hound::{SampleFormat, WavSpec, WavWriter};
+use std::error;
+use std::fs;
+use std::io;
+
+// writes buzzer output to a 16 bit mono pcm wav file. the writer fills in the
+// header when it is dropped, so a recording cut short by an error is still valid
+pub struct AudioRecorder {
+    writer: WavWriter<io::BufWriter<fs::File>>,
+    filename: String,
+}
+
+impl AudioRecorder {
+    pub fn new(filename: &str, sample_rate: i32) -> Result<Self, Box<dyn error::Error>> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: sample_rate as u32,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+
+        let writer = WavWriter::create(filename, spec)
+            .map_err(|e| format!("could not create '{}': {}", filename, e))?;
+
+        Ok(AudioRecorder {
+            writer,
+            filename: filename.to_string(),
+        })
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn write(&mut self, samples: &[f32]) -> Result<(), Box<dyn error::Error>> {
+        for sample in samples {
+            let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+            self.writer.write_sample(value)?;
+        }
+
+        Ok(())
+    }
+
+    // write the wav header sizes, the file is not valid until this is called
+    pub fn finish(self) -> Result<(), Box<dyn error::Error>> {
+        self.writer.finalize()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+mod audio_recorder;
+mod screenshot;
+mod video_recorder;
+
+#[cfg(test)]
+mod recording_tests;
+
+pub use self::audio_recorder::AudioRecorder;
+pub use self::screenshot::Screenshotter;
+pub use self::video_recorder::VideoRecorder;
+
+use crate::processor::pixel;
+use crate::processor::Framebuffer;
+use crate::processor::CHIP8_HEIGHT;
+use crate::processor::CHIP8_WIDTH;
+use std::path::Path;
+use std::time;
+
+// file name for a recording started without an explicit path. a number is
+// added when a file from the same second already exists, so two recordings
+// or screenshots in quick succession don't overwrite each other
+pub fn timestamped_filename(prefix: &str, extension: &str) -> String {
+    let seconds = time::SystemTime::now()
+        .duration_since(time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut filename = format!("{}-{}.{}", prefix, seconds, extension);
+    let mut number = 2;
+    while Path::new(&filename).exists() {
+        filename = format!("{}-{}-{}.{}", prefix, seconds, number, extension);
+        number += 1;
+    }
+    filename
+}
+
+// one palette index per output pixel
+fn scale_display(display: &Framebuffer, scale: u32) -> Vec<u8> {
+    let width = CHIP8_WIDTH * scale;
+    let height = CHIP8_HEIGHT * scale;
+    let mut pixels = Vec::with_capacity((width * height) as usize);
+
+    for y in 0..height {
+        for x in 0..width {
+            pixels.push(pixel(display, (x / scale) as usize, (y / scale) as usize) as u8);
+        }
+    }
+
+    pixels
+}
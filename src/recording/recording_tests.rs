@@ -0,0 +1,37 @@
+use super::VideoRecorder;
+use crate::palette::Palette;
+use std::env;
+use std::fs;
+
+// a recorder dropped without finish, as when the emulator stops on an error,
+// still leaves a complete file
+#[test]
+fn test_unfinished_video() {
+    for extension in ["gif", "png"] {
+        let path = env::temp_dir().join(format!(
+            "chip8-unfinished-{}.{}",
+            std::process::id(),
+            extension
+        ));
+        let filename = path.to_str().unwrap();
+
+        let mut recorder = VideoRecorder::new(filename, &Palette::default(), 1).unwrap();
+        let mut display = [0; 32];
+        for frame in 0..4 {
+            display[frame] = u64::MAX;
+            recorder.add_frame(&display).unwrap();
+        }
+        drop(recorder);
+
+        let file = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        match extension {
+            "gif" => assert_eq!(file.last(), Some(&0x3b)),
+            _ => {
+                let decoder = png::Decoder::new(std::io::Cursor::new(&file));
+                let reader = decoder.read_info().unwrap();
+                assert_eq!(reader.info().animation_control().unwrap().num_frames, 4);
+            }
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::scale_display;
+use super::timestamped_filename;
+use crate::palette::Palette;
+use crate::processor::Framebuffer;
+use crate::processor::CHIP8_HEIGHT;
+use crate::processor::CHIP8_WIDTH;
+use arboard::{Clipboard, ImageData};
+use std::borrow::Cow;
+use std::error;
+use std::fs;
+use std::io;
+
+// writes the display to timestamped png files, optionally also placing the
+// image on the clipboard
+pub struct Screenshotter {
+    palette: Palette,
+    scale: u32,
+    // on x11 the clipboard contents are served by this process, so the handle
+    // has to outlive the screenshot
+    clipboard: Option<Clipboard>,
+}
+
+impl Screenshotter {
+    // a scale of 1 gives one image pixel per chip8 pixel
+    pub fn new(
+        palette: &Palette,
+        scale: u32,
+        clipboard: bool,
+    ) -> Result<Self, Box<dyn error::Error>> {
+        let clipboard = if clipboard {
+            Some(Clipboard::new().map_err(|e| format!("could not open clipboard: {}", e))?)
+        } else {
+            None
+        };
+
+        Ok(Screenshotter {
+            palette: *palette,
+            scale,
+            clipboard,
+        })
+    }
+
+    pub fn set_palette(&mut self, palette: &Palette) {
+        self.palette = *palette;
+    }
+
+    // returns the name of the written file
+    pub fn take(&mut self, display: &Framebuffer) -> Result<String, Box<dyn error::Error>> {
+        let filename = timestamped_filename("chip8-screenshot", "png");
+        let pixels = scale_display(display, self.scale);
+        let width = CHIP8_WIDTH * self.scale;
+        let height = CHIP8_HEIGHT * self.scale;
+
+        let file = fs::File::create(&filename)
+            .map_err(|e| format!("could not create '{}': {}", filename, e))?;
+        let mut encoder = png::Encoder::new(io::BufWriter::new(file), width, height);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(self.palette.indexed_colors().to_vec());
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&pixels)?;
+        writer.finish()?;
+
+        if let Some(clipboard) = &mut self.clipboard {
+            let colors = self.palette.indexed_colors();
+            let rgba: Vec<u8> = pixels
+                .iter()
+                .flat_map(|&i| {
+                    let c = &colors[i as usize * 3..i as usize * 3 + 3];
+                    vec![c[0], c[1], c[2], 0xff]
+                })
+                .collect();
+
+            clipboard
+                .set_image(ImageData {
+                    width: width as usize,
+                    height: height as usize,
+                    bytes: Cow::Owned(rgba),
+                })
+                .map_err(|e| format!("could not copy screenshot to clipboard: {}", e))?;
+        }
+
+        Ok(filename)
+    }
+}
@@ -0,0 +1,180 @@
+use super::scale_display;
+use crate::palette::Palette;
+use crate::processor::Framebuffer;
+use crate::processor::CHIP8_HEIGHT;
+use crate::processor::CHIP8_WIDTH;
+use crate::FRAME_RATE;
+use gif::{Frame, Repeat};
+use std::borrow::Cow;
+use std::error;
+use std::fs;
+use std::io;
+
+// captures the display once per frame into an animated gif or apng, depending
+// on the file extension. runs of identical frames are stored as a single frame
+// shown for the length of the run. a recorder that is dropped without being
+// finished, when the emulator stops on an error, finishes the file itself
+pub struct VideoRecorder {
+    encoder: Option<Encoder>, // taken once the file is finished
+    filename: String,
+    scale: u32,
+    pending: Option<(Framebuffer, u32)>, // frame not yet written and the frame it started on
+    frame_count: u32,
+}
+
+enum Encoder {
+    Gif(gif::Encoder<io::BufWriter<fs::File>>),
+    // apng needs the number of frames up front, so frames are kept until the end
+    Apng {
+        file: io::BufWriter<fs::File>,
+        palette: Palette,
+        frames: Vec<(Vec<u8>, u32)>,
+    },
+}
+
+impl VideoRecorder {
+    pub fn new(
+        filename: &str,
+        palette: &Palette,
+        scale: u32,
+    ) -> Result<Self, Box<dyn error::Error>> {
+        let file = fs::File::create(filename)
+            .map_err(|e| format!("could not create '{}': {}", filename, e))?;
+        let file = io::BufWriter::new(file);
+
+        let encoder = if filename.to_lowercase().ends_with(".gif") {
+            let mut encoder = gif::Encoder::new(
+                file,
+                (CHIP8_WIDTH * scale) as u16,
+                (CHIP8_HEIGHT * scale) as u16,
+                &palette.indexed_colors(),
+            )?;
+            encoder.set_repeat(Repeat::Infinite)?;
+            Encoder::Gif(encoder)
+        } else if filename.to_lowercase().ends_with(".png") {
+            Encoder::Apng {
+                file,
+                palette: *palette,
+                frames: Vec::new(),
+            }
+        } else {
+            return Err(format!("'{}' is not a .gif or .png file", filename).into());
+        };
+
+        Ok(VideoRecorder {
+            encoder: Some(encoder),
+            filename: filename.to_string(),
+            scale,
+            pending: None,
+            frame_count: 0,
+        })
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    // add the display as it is at the end of a frame
+    pub fn add_frame(&mut self, display: &Framebuffer) -> Result<(), Box<dyn error::Error>> {
+        match self.pending {
+            Some((pending, _)) if pending == *display => {}
+            _ => {
+                self.write_pending()?;
+                self.pending = Some((*display, self.frame_count));
+            }
+        }
+
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> Result<(), Box<dyn error::Error>> {
+        self.finish_file()
+    }
+
+    fn finish_file(&mut self) -> Result<(), Box<dyn error::Error>> {
+        self.write_pending()?;
+
+        match self.encoder.take() {
+            Some(Encoder::Gif(encoder)) => {
+                encoder.into_inner()?;
+            }
+            Some(Encoder::Apng {
+                file,
+                palette,
+                frames,
+            }) => write_apng(file, &palette, &frames, self.scale)?,
+            None => {}
+        }
+
+        Ok(())
+    }
+
+    fn write_pending(&mut self) -> Result<(), Box<dyn error::Error>> {
+        let (display, start) = match self.pending.take() {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        let pixels = scale_display(&display, self.scale);
+
+        match &mut self.encoder {
+            Some(Encoder::Gif(encoder)) => {
+                // gif delays are in hundredths of a second, rounding the start and end
+                // separately keeps the total length from drifting
+                let delay = centiseconds(self.frame_count) - centiseconds(start);
+                let frame = Frame {
+                    width: (CHIP8_WIDTH * self.scale) as u16,
+                    height: (CHIP8_HEIGHT * self.scale) as u16,
+                    delay: delay.min(u16::MAX as u32) as u16,
+                    buffer: Cow::Owned(pixels),
+                    ..Frame::default()
+                };
+                encoder.write_frame(&frame)?;
+            }
+            Some(Encoder::Apng { frames, .. }) => frames.push((pixels, self.frame_count - start)),
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for VideoRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.finish_file() {
+            eprintln!("could not finish '{}': {}", self.filename, e);
+        }
+    }
+}
+
+fn write_apng(
+    file: io::BufWriter<fs::File>,
+    palette: &Palette,
+    frames: &[(Vec<u8>, u32)],
+    scale: u32,
+) -> Result<(), Box<dyn error::Error>> {
+    let mut encoder = png::Encoder::new(file, CHIP8_WIDTH * scale, CHIP8_HEIGHT * scale);
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(palette.indexed_colors().to_vec());
+    encoder.set_animated(frames.len().max(1) as u32, 0)?;
+
+    let mut writer = encoder.write_header()?;
+
+    if frames.is_empty() {
+        writer.write_image_data(&scale_display(&[0; CHIP8_HEIGHT as usize], scale))?;
+    }
+
+    for (pixels, length) in frames {
+        writer.set_frame_delay((*length).min(u16::MAX as u32) as u16, FRAME_RATE as u16)?;
+        writer.write_image_data(pixels)?;
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+fn centiseconds(frame: u32) -> u32 {
+    (frame * 100 + FRAME_RATE / 2) / FRAME_RATE
+}
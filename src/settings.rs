@@ -0,0 +1,301 @@
+use crate::config::Config;
+use crate::database::{Database, RomInfo};
+use crate::io::Waveform;
+use crate::keymap::Keymap;
+use crate::palette::Palette;
+use crate::processor::Quirks;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use toml_edit::{DocumentMut, Item};
+
+// settings that can be stored in the config file, either as global defaults or
+// for a single rom. unset values are left to the layer below, in order the
+// built in defaults, the global defaults, the rom section and the command line
+#[derive(Clone, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Settings {
+    #[serde(default)]
+    pub name: Option<String>, // rom file name, only there to make the file readable
+    #[serde(default)]
+    pub ipf: Option<u32>,
+    #[serde(default)]
+    pub start_address: Option<usize>,
+    #[serde(default, with = "as_string")]
+    pub quirks: Option<Quirks>,
+    #[serde(default, with = "as_string")]
+    pub palette: Option<Palette>,
+    #[serde(default)]
+    pub keymap: Option<Keymap>,
+    #[serde(default)]
+    pub mute: Option<bool>,
+    #[serde(default, with = "as_string")]
+    pub waveform: Option<Waveform>,
+    #[serde(default)]
+    pub frequency: Option<f32>,
+    #[serde(default)]
+    pub volume: Option<f32>,
+    #[serde(default)]
+    pub attack: Option<f32>,
+    #[serde(default)]
+    pub release: Option<f32>,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SettingsFile {
+    #[serde(default)]
+    pub defaults: Settings,
+    #[serde(default)]
+    pub rom: BTreeMap<String, Settings>, // keyed by the sha-1 of the rom file
+}
+
+impl Settings {
+    // values out of range are rejected the same way from the command line and
+    // the config file, prefix is put before the name in the message
+    pub fn check(&self, prefix: &str) -> Result<(), String> {
+        let ipf = self.ipf.map(|ipf| ipf as f32);
+        check(prefix, "ipf", ipf, |x| x >= 1.0, "at least 1")?;
+        check(prefix, "frequency", self.frequency, |x| x > 0.0, "above 0")?;
+        let volume = |x| (0.0..=1.0).contains(&x);
+        check(prefix, "volume", self.volume, volume, "between 0 and 1")?;
+        check(prefix, "attack", self.attack, |x| x >= 0.0, "0 or more")?;
+        check(prefix, "release", self.release, |x| x >= 0.0, "0 or more")?;
+        Ok(())
+    }
+
+    // only the values that differ from base
+    pub fn changed_from(&self, base: &Settings) -> Settings {
+        Settings {
+            name: self.name.clone(),
+            ipf: changed(&self.ipf, &base.ipf),
+            start_address: changed(&self.start_address, &base.start_address),
+            quirks: changed(&self.quirks, &base.quirks),
+            palette: changed(&self.palette, &base.palette),
+            keymap: changed(&self.keymap, &base.keymap),
+            mute: changed(&self.mute, &base.mute),
+            waveform: changed(&self.waveform, &base.waveform),
+            frequency: changed(&self.frequency, &base.frequency),
+            volume: changed(&self.volume, &base.volume),
+            attack: changed(&self.attack, &base.attack),
+            release: changed(&self.release, &base.release),
+        }
+    }
+}
+
+impl SettingsFile {
+    // $XDG_CONFIG_HOME/chip8/config.toml on linux
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("chip8").join("config.toml"))
+    }
+
+    // a missing file is the same as an empty one
+    pub fn load(path: &Path) -> Result<Self, Box<dyn error::Error>> {
+        if !path.exists() {
+            return Ok(SettingsFile::default());
+        }
+
+        let text = fs::read_to_string(path)?;
+        let file: SettingsFile = toml::from_str(&text)
+            .map_err(|e| format!("could not read config '{}': {}", path.display(), e))?;
+
+        let invalid =
+            |section: &str, e: String| format!("config '{}', [{}]: {}", path.display(), section, e);
+        file.defaults
+            .check("")
+            .map_err(|e| invalid("defaults", e))?;
+        for (hash, settings) in &file.rom {
+            settings
+                .check("")
+                .map_err(|e| invalid(&format!("rom.{}", hash), e))?;
+        }
+
+        Ok(file)
+    }
+}
+
+// apply the global settings from the config file, what the database knows
+// about the rom, the rom's own section and then the command line
+pub fn apply_settings(config: &mut Config, program: &[u8]) -> Result<(), Box<dyn error::Error>> {
+    let hash = rom_hash(program);
+    let file = match settings_path(config) {
+        Some(path) => SettingsFile::load(&path)?,
+        None => SettingsFile::default(),
+    };
+
+    config.apply(&file.defaults);
+
+    if let Some(info) = lookup_rom(config, &hash)? {
+        match &info.platform {
+            Some(platform) => println!("{} ({})", info.title, platform),
+            None => println!("{}", info.title),
+        }
+        if !info.authors.is_empty() {
+            println!("by {}", info.authors.join(", "));
+        }
+
+        config.apply(&info.settings);
+        config.title = Some(info.title);
+        config.key_hints = info.key_hints;
+    }
+
+    if let Some(settings) = file.rom.get(&hash) {
+        config.apply(settings);
+    }
+
+    let overrides = config.overrides.clone();
+    config.apply(&overrides);
+    Ok(())
+}
+
+// store the current settings as the section for the running rom, keeping only
+// the values that differ from the global defaults and the database. the rest
+// of the file is left as it was, comments included
+pub fn save_rom_settings(
+    config: &Config,
+    program: &[u8],
+    current: &Settings,
+) -> Result<PathBuf, Box<dyn error::Error>> {
+    let path = settings_path(config).ok_or("no config directory found, use --config")?;
+    let file = SettingsFile::load(&path)?;
+    let mut document: DocumentMut = match path.exists() {
+        true => fs::read_to_string(&path)?.parse()?,
+        false => DocumentMut::new(),
+    };
+
+    let mut base = Config::new(&config.filename);
+    base.apply(&file.defaults);
+    if let Some(info) = lookup_rom(config, &rom_hash(program))? {
+        base.apply(&info.settings);
+    }
+
+    let mut settings = current.changed_from(&base.settings());
+    settings.name = Path::new(&config.filename)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned());
+    let section: DocumentMut = toml::to_string(&settings)?.parse()?;
+
+    let roms = document
+        .entry("rom")
+        .or_insert(toml_edit::table())
+        .as_table_mut()
+        .ok_or("'rom' in the config file is not a table")?;
+    roms.set_implicit(true);
+    roms.insert(&rom_hash(program), Item::Table(section.as_table().clone()));
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, document.to_string())?;
+    Ok(path)
+}
+
+// the database is optional unless one was asked for on the command line. its
+// values are checked like the config file's, but it isn't the user's file to
+// fix, so a bad one is left out with a warning instead of stopping the load
+fn lookup_rom(config: &Config, hash: &str) -> Result<Option<RomInfo>, Box<dyn error::Error>> {
+    let mut info = match load_database(config)?.and_then(|database| database.lookup(hash)) {
+        Some(info) => info,
+        None => return Ok(None),
+    };
+
+    // the tickrate is the only checked value the database sets
+    if let Err(e) = info.settings.check("") {
+        eprintln!(
+            "ignoring the database's tickrate for '{}': {}",
+            info.title, e
+        );
+        info.settings.ipf = None;
+    }
+    Ok(Some(info))
+}
+
+// the database given with --database, or the default one if it is installed.
+// it is read once and kept, settings are looked up on every load and save
+pub fn load_database(config: &Config) -> Result<Option<Arc<Database>>, Box<dyn error::Error>> {
+    static DATABASES: Mutex<BTreeMap<PathBuf, Arc<Database>>> = Mutex::new(BTreeMap::new());
+
+    let path = match &config.database {
+        Some(path) => path.clone(),
+        None => match Database::default_path() {
+            Some(path) if path.exists() => path,
+            _ => return Ok(None),
+        },
+    };
+
+    let mut databases = DATABASES.lock().map_err(|e| e.to_string())?;
+    if let Some(database) = databases.get(&path) {
+        return Ok(Some(database.clone()));
+    }
+    let database = Arc::new(Database::load(&path)?);
+    databases.insert(path, database.clone());
+    Ok(Some(database))
+}
+
+fn settings_path(config: &Config) -> Option<PathBuf> {
+    config
+        .config_file
+        .clone()
+        .or_else(SettingsFile::default_path)
+}
+
+// the key used for per-rom sections
+pub fn rom_hash(program: &[u8]) -> String {
+    sha1_smol::Sha1::from(program).digest().to_string()
+}
+
+fn check(
+    prefix: &str,
+    name: &str,
+    value: Option<f32>,
+    valid: impl Fn(f32) -> bool,
+    expected: &str,
+) -> Result<(), String> {
+    match value {
+        Some(x) if !x.is_finite() || !valid(x) => {
+            Err(format!("'{}{}' must be {}", prefix, name, expected))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn changed<T: Clone + PartialEq>(value: &Option<T>, base: &Option<T>) -> Option<T> {
+    if value != base {
+        value.clone()
+    } else {
+        None
+    }
+}
+
+// store a value as the string its Display and FromStr implementations use
+mod as_string {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        match Option::<String>::deserialize(deserializer)? {
+            Some(s) => s.parse().map(Some).map_err(D::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use crate::config::Config;
+use crate::io::Waveform;
+use crate::palette::Palette;
+use crate::processor::Quirks;
+use crate::settings::*;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+const PROGRAM: [u8; 2] = [0x12, 0x00];
+const OTHER_PROGRAM: [u8; 2] = [0x12, 0x02];
+
+// a config file and an empty database of the test's own, so the user's files
+// are never read
+fn setup(test: &str, text: &str) -> (Config, PathBuf) {
+    let dir = env::temp_dir().join(format!("chip8-{}-{}", test, std::process::id()));
+    let database = dir.join("database");
+    fs::create_dir_all(&database).unwrap();
+    fs::write(database.join("programs.json"), "[]").unwrap();
+    fs::write(database.join("platforms.json"), "[]").unwrap();
+    fs::write(dir.join("config.toml"), text).unwrap();
+
+    let mut config = Config::new("game.ch8");
+    config.config_file = Some(dir.join("config.toml"));
+    config.database = Some(database);
+    (config, dir)
+}
+
+#[test]
+fn test_load() {
+    let (config, dir) = setup(
+        "settings_load",
+        "[defaults]\nipf = 20\npalette = \"green\"\nwaveform = \"sine\"\n",
+    );
+    let file = SettingsFile::load(config.config_file.as_ref().unwrap()).unwrap();
+    let missing = SettingsFile::load(&dir.join("missing.toml")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(file.defaults.ipf, Some(20));
+    assert!(file.defaults.palette == Some("green".parse().unwrap()));
+    assert!(file.defaults.waveform == Some(Waveform::Sine));
+    assert!(file.defaults.quirks.is_none());
+    assert!(file.rom.is_empty());
+    assert!(missing.defaults.ipf.is_none());
+}
+
+#[test]
+fn test_load_invalid() {
+    let texts = [
+        "[defaults]\nvolume = 2.0\n",
+        "[defaults]\nipf = 0\n",
+        "[rom.aaaa]\nfrequency = 0.0\n",
+        "[defaults]\nspeed = 10\n",
+        "[defaults]\nquirks = \"cosmac\"\n",
+    ];
+
+    for text in texts {
+        let (config, dir) = setup("settings_load_invalid", text);
+        let result = SettingsFile::load(config.config_file.as_ref().unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err(), "{}", text);
+    }
+}
+
+#[test]
+fn test_rom_settings_over_defaults() {
+    let text = format!(
+        "[defaults]\nipf = 20\npalette = \"green\"\n\n[rom.{}]\nipf = 30\n",
+        rom_hash(&PROGRAM)
+    );
+    let (mut config, dir) = setup("settings_merge", &text);
+    let mut other = config.clone();
+    let mut overridden = config.clone();
+    overridden.overrides.palette = Some("amber".parse().unwrap());
+
+    apply_settings(&mut config, &PROGRAM).unwrap();
+    apply_settings(&mut other, &OTHER_PROGRAM).unwrap();
+    apply_settings(&mut overridden, &PROGRAM).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let green: Palette = "green".parse().unwrap();
+    assert_eq!(config.instructions_per_frame, 30);
+    assert!(config.palette == green);
+    assert_eq!(other.instructions_per_frame, 20);
+    assert!(other.palette == green);
+    // the command line comes last
+    assert_eq!(overridden.instructions_per_frame, 30);
+    assert!(overridden.palette == "amber".parse().unwrap());
+}
+
+#[test]
+fn test_save_rom_settings() {
+    let text = "# kept as written\n[defaults]\nipf = 20 # a bit faster\n";
+    let (mut config, dir) = setup("settings_save", text);
+    apply_settings(&mut config, &PROGRAM).unwrap();
+
+    let mut current = config.settings();
+    current.quirks = Some(Quirks::chip8());
+    let path = save_rom_settings(&config, &PROGRAM, &current).unwrap();
+    let saved = fs::read_to_string(&path).unwrap();
+    let file = SettingsFile::load(&path).unwrap();
+
+    let mut reloaded = config.clone();
+    apply_settings(&mut reloaded, &PROGRAM).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(saved.starts_with(text));
+    // only what differs from the defaults is stored
+    let section = &file.rom[&rom_hash(&PROGRAM)];
+    assert_eq!(section.name.as_deref(), Some("game.ch8"));
+    assert_eq!(section.quirks, Some(Quirks::chip8()));
+    assert!(section.ipf.is_none());
+    assert!(section.palette.is_none());
+    assert_eq!(reloaded.cpu.quirks, Quirks::chip8());
+    assert_eq!(reloaded.instructions_per_frame, 20);
+}
+
+#[test]
+fn test_palette_from_str() {
+    let palette: Palette = "#0f380f,9bbc0f".parse().unwrap();
+    assert_eq!(palette.background, (15, 56, 15));
+    assert_eq!(palette.pixel, (155, 188, 15));
+    assert!("green".parse::<Palette>().unwrap() == palette);
+    assert_eq!(palette.to_string(), "0f380f,9bbc0f");
+    assert!(palette.to_string().parse::<Palette>().unwrap() == palette);
+
+    assert!("purple".parse::<Palette>().is_err());
+    assert!("0f380f".parse::<Palette>().is_err());
+    assert!("0f380f,9bbc0".parse::<Palette>().is_err());
+    assert!("0f380f,9bbc0g".parse::<Palette>().is_err());
+}
+
+#[test]
+fn test_waveform_from_str() {
+    for name in ["square", "sine", "triangle", "noise"] {
+        let waveform: Waveform = name.parse().unwrap();
+        assert_eq!(waveform.to_string(), name);
+    }
+    assert!("Sine".parse::<Waveform>().is_err());
+    assert!("saw".parse::<Waveform>().is_err());
+}
+
+#[test]
+fn test_invalid_database_tickrate() {
+    let (mut config, dir) = setup("settings_database_tickrate", "");
+    let programs = format!(
+        r#"[{{ "title": "Zero", "roms": {{ "{}": {{ "tickrate": 0 }} }} }}]"#,
+        rom_hash(&PROGRAM)
+    );
+    let database = config.database.clone().unwrap();
+    fs::write(database.join("programs.json"), programs).unwrap();
+
+    apply_settings(&mut config, &PROGRAM).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    // the rest of the entry still applies
+    assert_eq!(config.title.as_deref(), Some("Zero"));
+    assert_eq!(
+        config.instructions_per_frame,
+        Config::new("").instructions_per_frame
+    );
+}
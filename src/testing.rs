@@ -0,0 +1,258 @@
+use crate::processor::{pixel, Cpu, CpuOptions, Framebuffer, Row, CHIP8_HEIGHT, CHIP8_WIDTH};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+// set to write the golden files from the current output instead of checking
+// against them, e.g. `CHIP8_BLESS=1 cargo test`
+pub const BLESS_VARIABLE: &str = "CHIP8_BLESS";
+
+const WIDTH: usize = CHIP8_WIDTH as usize;
+const HEIGHT: usize = CHIP8_HEIGHT as usize;
+
+// a whole program running for a test: load it, run frames with keys held,
+// then check the screen against a golden file or look at the cpu directly.
+// failures panic with a message, like assert does
+pub struct RomTest {
+    pub cpu: Cpu,
+    pub instructions_per_frame: u32,
+    keys: [bool; 16],
+    frames: u32,
+}
+
+impl RomTest {
+    // random numbers are seeded so runs are repeatable
+    pub fn load<P: AsRef<Path>>(filename: P) -> Self {
+        let options = CpuOptions {
+            seed: Some(0),
+            ..CpuOptions::default()
+        };
+        Self::with_options(filename, &options)
+    }
+
+    pub fn with_options<P: AsRef<Path>>(filename: P, options: &CpuOptions) -> Self {
+        let filename = filename.as_ref();
+        let program = fs::read(filename)
+            .unwrap_or_else(|e| panic!("could not read '{}': {}", filename.display(), e));
+        Self::from_program(&program, options)
+    }
+
+    pub fn from_program(program: &[u8], options: &CpuOptions) -> Self {
+        let cpu = Cpu::with_options(program, options).unwrap_or_else(|e| panic!("{}", e));
+        RomTest {
+            cpu,
+            instructions_per_frame: 10,
+            keys: [false; 16],
+            frames: 0,
+        }
+    }
+
+    pub fn run_frames(&mut self, frames: u32) -> &mut Self {
+        for _ in 0..frames {
+            let keys = self.keys;
+            self.cpu
+                .run_frame(&keys, self.instructions_per_frame)
+                .unwrap_or_else(|e| panic!("frame {}: {}", self.frames, e));
+            self.frames += 1;
+        }
+        self
+    }
+
+    // keys stay held until released
+    pub fn press(&mut self, key: usize) -> &mut Self {
+        self.keys[key] = true;
+        self
+    }
+
+    pub fn release(&mut self, key: usize) -> &mut Self {
+        self.keys[key] = false;
+        self
+    }
+
+    // hold a key for a number of frames, then let go
+    pub fn tap(&mut self, key: usize, frames: u32) -> &mut Self {
+        self.press(key).run_frames(frames).release(key)
+    }
+
+    // frames run since the program was loaded
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    // the display as text, # for pixels that are on
+    pub fn screen(&self) -> String {
+        to_ascii(&self.cpu.display)
+    }
+
+    // compare the display with a golden file, plain pbm if the name ends in
+    // .pbm and text otherwise. with CHIP8_BLESS set the file is written instead
+    pub fn assert_screen<P: AsRef<Path>>(&self, golden: P) {
+        let golden = golden.as_ref();
+        let pbm = golden.extension().is_some_and(|e| e == "pbm");
+
+        if env::var_os(BLESS_VARIABLE).is_some() {
+            if let Some(parent) = golden.parent() {
+                fs::create_dir_all(parent).unwrap_or_else(|e| panic!("{}", e));
+            }
+            let text = if pbm {
+                to_pbm(&self.cpu.display)
+            } else {
+                to_ascii(&self.cpu.display)
+            };
+            fs::write(golden, text)
+                .unwrap_or_else(|e| panic!("could not write '{}': {}", golden.display(), e));
+            return;
+        }
+
+        let text = fs::read_to_string(golden).unwrap_or_else(|e| {
+            panic!(
+                "could not read '{}': {}, run with {}=1 to create it",
+                golden.display(),
+                e,
+                BLESS_VARIABLE
+            )
+        });
+        let expected = if pbm {
+            parse_pbm(&text)
+        } else {
+            parse_ascii(&text)
+        }
+        .unwrap_or_else(|e| panic!("{}: {}", golden.display(), e));
+
+        if expected != self.cpu.display {
+            panic!(
+                "screen after {} frames does not match '{}', run with {}=1 to update it\n{}",
+                self.frames,
+                golden.display(),
+                BLESS_VARIABLE,
+                side_by_side(&expected, &self.cpu.display)
+            );
+        }
+    }
+
+    pub fn assert_register(&self, register: usize, value: u8) {
+        let actual = self.cpu.register[register];
+        assert_eq!(
+            actual, value,
+            "v{:x} is {:#04x}, expected {:#04x} after {} frames",
+            register, actual, value, self.frames
+        );
+    }
+
+    pub fn assert_memory(&self, address: usize, bytes: &[u8]) {
+        let actual = &self.cpu.memory()[address..address + bytes.len()];
+        assert_eq!(
+            actual, bytes,
+            "memory at {:#05x} after {} frames",
+            address, self.frames
+        );
+    }
+}
+
+pub fn to_ascii(display: &Framebuffer) -> String {
+    let mut text = String::with_capacity((WIDTH + 1) * HEIGHT);
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            text.push(if pixel(display, x, y) { '#' } else { '.' });
+        }
+        text.push('\n');
+    }
+    text
+}
+
+// plain (P1) pbm, which image viewers can open
+pub fn to_pbm(display: &Framebuffer) -> String {
+    let mut text = format!("P1\n{} {}\n", WIDTH, HEIGHT);
+    for y in 0..HEIGHT {
+        let pixels: Vec<String> = (0..WIDTH)
+            .map(|x| (pixel(display, x, y) as u8).to_string())
+            .collect();
+        text += &pixels.join(" ");
+        text.push('\n');
+    }
+    text
+}
+
+pub fn parse_ascii(text: &str) -> Result<Framebuffer, String> {
+    let rows: Vec<&str> = text.lines().map(|l| l.trim_end()).collect();
+    if rows.len() != HEIGHT {
+        return Err(format!("expected {} rows, found {}", HEIGHT, rows.len()));
+    }
+
+    let mut display = Vec::with_capacity(WIDTH * HEIGHT);
+    for (y, row) in rows.iter().enumerate() {
+        if row.chars().count() != WIDTH {
+            return Err(format!("row {} is not {} pixels wide", y + 1, WIDTH));
+        }
+        for c in row.chars() {
+            match c {
+                '.' => display.push(0),
+                '#' => display.push(1),
+                _ => return Err(format!("unexpected '{}' in row {}", c, y + 1)),
+            }
+        }
+    }
+    Ok(pack(&display))
+}
+
+pub fn parse_pbm(text: &str) -> Result<Framebuffer, String> {
+    // comments run from # to the end of the line
+    let text: String = text
+        .lines()
+        .map(|l| l.split('#').next().unwrap_or(""))
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let mut tokens = text.split_whitespace();
+
+    if tokens.next() != Some("P1") {
+        return Err("not a plain pbm file, expected P1".to_string());
+    }
+    let size: Vec<usize> = tokens
+        .by_ref()
+        .take(2)
+        .map(|t| t.parse().map_err(|_| format!("invalid size '{}'", t)))
+        .collect::<Result<_, _>>()?;
+    if size != [WIDTH, HEIGHT] {
+        return Err(format!("expected a {}x{} image", WIDTH, HEIGHT));
+    }
+
+    // pixels don't need whitespace between them
+    let display: Vec<u8> = tokens
+        .flat_map(|t| t.chars())
+        .map(|c| match c {
+            '0' => Ok(0),
+            '1' => Ok(1),
+            _ => Err(format!("unexpected '{}' in pixels", c)),
+        })
+        .collect::<Result<_, _>>()?;
+    if display.len() != WIDTH * HEIGHT {
+        return Err(format!(
+            "expected {} pixels, found {}",
+            WIDTH * HEIGHT,
+            display.len()
+        ));
+    }
+    Ok(pack(&display))
+}
+
+// pixels one per byte, row by row, into display rows
+fn pack(pixels: &[u8]) -> Framebuffer {
+    let mut display = [0; HEIGHT];
+    for (row, pixels) in display.iter_mut().zip(pixels.chunks(WIDTH)) {
+        *row = pixels.iter().fold(0, |row, &p| row << 1 | p as Row);
+    }
+    display
+}
+
+// expected and actual next to each other, rows that differ marked with >
+fn side_by_side(expected: &Framebuffer, actual: &Framebuffer) -> String {
+    let expected = to_ascii(expected);
+    let actual = to_ascii(actual);
+
+    let mut text = format!("  {:<width$}  actual\n", "expected", width = WIDTH);
+    for (e, a) in expected.lines().zip(actual.lines()) {
+        let marker = if e == a { ' ' } else { '>' };
+        text += &format!("{} {}  {}\n", marker, e, a);
+    }
+    text
+}
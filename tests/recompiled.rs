@@ -0,0 +1,197 @@
+use chip8::processor::{recompile, Cpu, CpuOptions, FrameOutput, Quirks, Symbols};
+use chip8::testing::BLESS_VARIABLE;
+use std::env;
+use std::error;
+use std::fs;
+use std::path::PathBuf;
+
+// sources made by `chip8 recompile` from the roms in tests/roms, compiled in
+// and run next to the interpreter. CHIP8_BLESS=1 cargo test regenerates them.
+// their main and run open a window, only run_frame is used here
+#[path = "recompiled/alu.rs"]
+#[allow(dead_code)]
+mod alu;
+#[path = "recompiled/font.rs"]
+#[allow(dead_code)]
+mod font;
+#[path = "recompiled/keys.rs"]
+#[allow(dead_code)]
+mod keys;
+#[path = "recompiled/ops.rs"]
+#[allow(dead_code)]
+mod ops;
+
+type RunFrame = fn(&mut Cpu, &[bool; 16]) -> Result<FrameOutput, Box<dyn error::Error>>;
+
+// relative to the crate, cargo runs tests from the package directory but other
+// runners may not
+fn path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+}
+
+fn check_source(name: &str) {
+    let program = fs::read(path(&format!("roms/{}.ch8", name))).unwrap();
+    let source = recompile(
+        &program,
+        &CpuOptions::default(),
+        10,
+        &format!("{}.ch8", name),
+    );
+    let filename = path(&format!("recompiled/{}.rs", name));
+
+    if env::var_os(BLESS_VARIABLE).is_some() {
+        fs::write(&filename, source).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&filename).unwrap_or_default();
+    assert!(
+        source == expected,
+        "'{}' is out of date, run with {}=1 to update it",
+        filename.display(),
+        BLESS_VARIABLE
+    );
+}
+
+fn cpu(program: &[u8], start_address: usize, quirks: Quirks, profile: bool) -> Cpu {
+    let options = CpuOptions {
+        quirks,
+        start_address,
+        seed: Some(0),
+        profile,
+        ..CpuOptions::default()
+    };
+    Cpu::with_options(program, &options).unwrap()
+}
+
+// run the interpreter and a recompiled program side by side, frame by frame
+fn check_same(
+    program: &[u8],
+    start_address: usize,
+    run_frame: RunFrame,
+    frames: u32,
+    keys: fn(u32) -> [bool; 16],
+    quirks: Quirks,
+) {
+    let mut interpreted = cpu(program, start_address, quirks, false);
+    let mut recompiled = cpu(program, start_address, quirks, false);
+
+    for frame in 0..frames {
+        let a = interpreted.run_frame(&keys(frame), 10).unwrap();
+        let b = run_frame(&mut recompiled, &keys(frame)).unwrap();
+
+        let flags = |f: FrameOutput| (f.redraw_flag, f.sound_flag, f.audio_flag);
+        assert_eq!(flags(a), flags(b), "frame {}", frame);
+        assert_eq!(interpreted.program_counter, recompiled.program_counter);
+        assert_eq!(interpreted.register, recompiled.register);
+        assert_eq!(interpreted.register_i, recompiled.register_i);
+        assert_eq!(interpreted.stack, recompiled.stack);
+        assert_eq!(interpreted.stack_pointer, recompiled.stack_pointer);
+        assert_eq!(interpreted.delay_timer, recompiled.delay_timer);
+        assert_eq!(interpreted.sound_timer, recompiled.sound_timer);
+        assert_eq!(interpreted.pitch, recompiled.pitch);
+        assert_eq!(interpreted.keypad_waiting, recompiled.keypad_waiting);
+        assert_eq!(interpreted.cycles, recompiled.cycles);
+        assert_eq!(interpreted.memory()[..], recompiled.memory()[..]);
+        assert_eq!(interpreted.display, recompiled.display);
+    }
+}
+
+fn no_keys(_frame: u32) -> [bool; 16] {
+    [false; 16]
+}
+
+#[test]
+fn test_recompiled_alu() {
+    check_source("alu");
+    check_same(
+        &alu::PROGRAM,
+        alu::START_ADDRESS,
+        alu::run_frame,
+        10,
+        no_keys,
+        Quirks::default(),
+    );
+}
+
+#[test]
+fn test_recompiled_font() {
+    check_source("font");
+    check_same(
+        &font::PROGRAM,
+        font::START_ADDRESS,
+        font::run_frame,
+        30,
+        no_keys,
+        Quirks::default(),
+    );
+}
+
+#[test]
+fn test_recompiled_keys() {
+    check_source("keys");
+    check_same(
+        &keys::PROGRAM,
+        keys::START_ADDRESS,
+        keys::run_frame,
+        30,
+        |frame| {
+            let mut keys = [false; 16];
+            keys[0xa] = (5..7).contains(&frame);
+            keys[0x3] = (15..17).contains(&frame);
+            keys
+        },
+        Quirks::default(),
+    );
+}
+
+#[test]
+fn test_recompiled_ops() {
+    check_source("ops");
+
+    // translated instructions check the quirks when they run, so the same
+    // source works for every profile
+    for quirks in [Quirks::default(), Quirks::chip8(), Quirks::xochip()] {
+        check_same(
+            &ops::PROGRAM,
+            ops::START_ADDRESS,
+            ops::run_frame,
+            30,
+            |frame| {
+                let mut keys = [false; 16];
+                keys[frame as usize % 16] = frame % 3 == 0;
+                keys
+            },
+            quirks,
+        );
+    }
+}
+
+#[test]
+fn test_recompiled_profile() {
+    // with the profiler on every instruction goes through the interpreter and
+    // is counted the same
+    let mut interpreted = cpu(&ops::PROGRAM, ops::START_ADDRESS, Quirks::default(), true);
+    let mut recompiled = cpu(&ops::PROGRAM, ops::START_ADDRESS, Quirks::default(), true);
+    for _ in 0..10 {
+        interpreted.run_frame(&[false; 16], 10).unwrap();
+        ops::run_frame(&mut recompiled, &[false; 16]).unwrap();
+    }
+
+    let dir = env::temp_dir().join(format!("chip8-recompiled-profile-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let mut reports = Vec::new();
+    for (name, cpu) in [("interpreted", &interpreted), ("recompiled", &recompiled)] {
+        let filename = dir.join(name).to_string_lossy().to_string();
+        let profiler = cpu.profiler.as_ref().unwrap();
+        profiler
+            .write_report(&filename, cpu.memory(), &Symbols::default())
+            .unwrap();
+        reports.push(fs::read_to_string(&filename).unwrap());
+    }
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert!(!reports[0].is_empty());
+    assert_eq!(reports[0], reports[1]);
+}
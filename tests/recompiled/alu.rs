@@ -0,0 +1,135 @@
+// recompiled from alu.ch8 by chip8 recompile, regenerate it instead of editing.
+// build it as a binary of a crate that depends on chip8, src/main.rs for
+// example
+
+use chip8::host;
+use chip8::processor::operations::{self, Opcode};
+use chip8::processor::{Cpu, CpuOptions, FrameOutput};
+use std::error;
+use std::process;
+
+pub const START_ADDRESS: usize = 0x200;
+pub const QUIRKS: &str = "default";
+pub const INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+pub const PROGRAM: [u8; 28] = [
+    0x60, 0x14, 0x61, 0x33, 0x80, 0x14, 0x62, 0xf0, 0x63, 0x20, 0x82, 0x34, 0x84, 0xf0, 0x65, 0x05,
+    0x66, 0x08, 0x85, 0x65, 0x87, 0xf0, 0xa3, 0x00, 0xf7, 0x55, 0x12, 0x1a,
+];
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+// run the program in a window until it is closed
+pub fn run() -> Result<(), Box<dyn error::Error>> {
+    let options = CpuOptions {
+        quirks: QUIRKS.parse()?,
+        start_address: START_ADDRESS,
+        ..CpuOptions::default()
+    };
+    let mut cpu = Cpu::with_options(&PROGRAM, &options)?;
+    let (mut display, mut input, mut timer) = host::open_sdl("alu.ch8", 10)?;
+    host::run(&mut cpu, &mut display, &mut input, &mut timer, run_frame)
+}
+
+// one 60hz frame. the profiler, coverage and trace only see instructions
+// run by the interpreter, so the whole frame is interpreted when one is on
+pub fn run_frame(cpu: &mut Cpu, keys: &[bool; 16]) -> Result<FrameOutput, Box<dyn error::Error>> {
+    if cpu.instrumented() {
+        cpu.run_frame(keys, INSTRUCTIONS_PER_FRAME)
+    } else {
+        cpu.run_frame_with(keys, INSTRUCTIONS_PER_FRAME, execute)
+    }
+}
+
+// run the instruction at the program counter, for Cpu::run_frame_with
+pub fn execute(cpu: &mut Cpu) {
+    let pc = cpu.program_counter;
+    if cpu.rewritten(pc) {
+        cpu.execute();
+        return;
+    }
+    match pc {
+        // LD V0, 0x14
+        0x200 => {
+            cpu.cycles += 1;
+            cpu.register[0x0] = 0x14;
+        }
+        // LD V1, 0x33
+        0x202 => {
+            cpu.cycles += 1;
+            cpu.register[0x1] = 0x33;
+        }
+        // ADD V0, V1
+        0x204 => {
+            cpu.cycles += 1;
+            let (value, carry) = cpu.register[0x0].overflowing_add(cpu.register[0x1]);
+            cpu.register[0xf] = carry as u8;
+            cpu.register[0x0] = value;
+        }
+        // LD V2, 0xf0
+        0x206 => {
+            cpu.cycles += 1;
+            cpu.register[0x2] = 0xf0;
+        }
+        // LD V3, 0x20
+        0x208 => {
+            cpu.cycles += 1;
+            cpu.register[0x3] = 0x20;
+        }
+        // ADD V2, V3
+        0x20a => {
+            cpu.cycles += 1;
+            let (value, carry) = cpu.register[0x2].overflowing_add(cpu.register[0x3]);
+            cpu.register[0xf] = carry as u8;
+            cpu.register[0x2] = value;
+        }
+        // LD V4, VF
+        0x20c => {
+            cpu.cycles += 1;
+            cpu.register[0x4] = cpu.register[0xf];
+        }
+        // LD V5, 0x05
+        0x20e => {
+            cpu.cycles += 1;
+            cpu.register[0x5] = 0x05;
+        }
+        // LD V6, 0x08
+        0x210 => {
+            cpu.cycles += 1;
+            cpu.register[0x6] = 0x08;
+        }
+        // SUB V5, V6
+        0x212 => {
+            cpu.cycles += 1;
+            let (value, borrow) = cpu.register[0x5].overflowing_sub(cpu.register[0x6]);
+            cpu.register[0xf] = !borrow as u8;
+            cpu.register[0x5] = value;
+        }
+        // LD V7, VF
+        0x214 => {
+            cpu.cycles += 1;
+            cpu.register[0x7] = cpu.register[0xf];
+        }
+        // LD I, 0x300
+        0x216 => {
+            cpu.cycles += 1;
+            cpu.register_i = 0x300;
+        }
+        // LD [I], V7
+        0x218 => {
+            cpu.cycles += 1;
+            operations::store_registers(cpu, &Opcode::from(0xf755));
+        }
+        // JP 0x21a
+        0x21a => {
+            cpu.cycles += 1;
+            cpu.program_counter = 0x218;
+        }
+        _ => cpu.execute(),
+    }
+}
@@ -0,0 +1,143 @@
+// recompiled from font.ch8 by chip8 recompile, regenerate it instead of editing.
+// build it as a binary of a crate that depends on chip8, src/main.rs for
+// example
+
+use chip8::host;
+use chip8::processor::operations::{self, Opcode};
+use chip8::processor::{Cpu, CpuOptions, FrameOutput};
+use std::error;
+use std::process;
+
+pub const START_ADDRESS: usize = 0x200;
+pub const QUIRKS: &str = "default";
+pub const INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+pub const PROGRAM: [u8; 38] = [
+    0x60, 0x00, 0x61, 0x01, 0x62, 0x01, 0xf0, 0x29, 0xd1, 0x25, 0x71, 0x08, 0x70, 0x01, 0x40, 0x08,
+    0x22, 0x20, 0x30, 0x10, 0x12, 0x06, 0x12, 0x16, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0x61, 0x01, 0x62, 0x09, 0x00, 0xee,
+];
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+// run the program in a window until it is closed
+pub fn run() -> Result<(), Box<dyn error::Error>> {
+    let options = CpuOptions {
+        quirks: QUIRKS.parse()?,
+        start_address: START_ADDRESS,
+        ..CpuOptions::default()
+    };
+    let mut cpu = Cpu::with_options(&PROGRAM, &options)?;
+    let (mut display, mut input, mut timer) = host::open_sdl("font.ch8", 10)?;
+    host::run(&mut cpu, &mut display, &mut input, &mut timer, run_frame)
+}
+
+// one 60hz frame. the profiler, coverage and trace only see instructions
+// run by the interpreter, so the whole frame is interpreted when one is on
+pub fn run_frame(cpu: &mut Cpu, keys: &[bool; 16]) -> Result<FrameOutput, Box<dyn error::Error>> {
+    if cpu.instrumented() {
+        cpu.run_frame(keys, INSTRUCTIONS_PER_FRAME)
+    } else {
+        cpu.run_frame_with(keys, INSTRUCTIONS_PER_FRAME, execute)
+    }
+}
+
+// run the instruction at the program counter, for Cpu::run_frame_with
+pub fn execute(cpu: &mut Cpu) {
+    let pc = cpu.program_counter;
+    if cpu.rewritten(pc) {
+        cpu.execute();
+        return;
+    }
+    match pc {
+        // LD V0, 0x00
+        0x200 => {
+            cpu.cycles += 1;
+            cpu.register[0x0] = 0x00;
+        }
+        // LD V1, 0x01
+        0x202 => {
+            cpu.cycles += 1;
+            cpu.register[0x1] = 0x01;
+        }
+        // LD V2, 0x01
+        0x204 => {
+            cpu.cycles += 1;
+            cpu.register[0x2] = 0x01;
+        }
+        // LD F, V0
+        0x206 => {
+            cpu.cycles += 1;
+            cpu.register_i = cpu.register[0x0] as u16 * 5;
+        }
+        // DRW V1, V2, 5
+        0x208 => {
+            cpu.cycles += 1;
+            operations::draw(cpu, &Opcode::from(0xd125));
+        }
+        // ADD V1, 0x08
+        0x20a => {
+            cpu.cycles += 1;
+            let (value, carry) = cpu.register[0x1].overflowing_add(0x08);
+            cpu.register[0xf] = carry as u8;
+            cpu.register[0x1] = value;
+        }
+        // ADD V0, 0x01
+        0x20c => {
+            cpu.cycles += 1;
+            let (value, carry) = cpu.register[0x0].overflowing_add(0x01);
+            cpu.register[0xf] = carry as u8;
+            cpu.register[0x0] = value;
+        }
+        // SNE V0, 0x08
+        0x20e => {
+            cpu.cycles += 1;
+            if cpu.register[0x0] != 0x08 {
+                cpu.program_counter += 2;
+            }
+        }
+        // CALL 0x220
+        0x210 => {
+            cpu.cycles += 1;
+            operations::call_subroutine(cpu, &Opcode::from(0x2220));
+        }
+        // SE V0, 0x10
+        0x212 => {
+            cpu.cycles += 1;
+            if cpu.register[0x0] == 0x10 {
+                cpu.program_counter += 2;
+            }
+        }
+        // JP 0x206
+        0x214 => {
+            cpu.cycles += 1;
+            cpu.program_counter = 0x204;
+        }
+        // JP 0x216
+        0x216 => {
+            cpu.cycles += 1;
+            cpu.program_counter = 0x214;
+        }
+        // LD V1, 0x01
+        0x220 => {
+            cpu.cycles += 1;
+            cpu.register[0x1] = 0x01;
+        }
+        // LD V2, 0x09
+        0x222 => {
+            cpu.cycles += 1;
+            cpu.register[0x2] = 0x09;
+        }
+        // RET
+        0x224 => {
+            cpu.cycles += 1;
+            operations::return_from_subroutine(cpu, &Opcode::from(0x00ee));
+        }
+        _ => cpu.execute(),
+    }
+}
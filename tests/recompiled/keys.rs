@@ -0,0 +1,95 @@
+// recompiled from keys.ch8 by chip8 recompile, regenerate it instead of editing.
+// build it as a binary of a crate that depends on chip8, src/main.rs for
+// example
+
+use chip8::host;
+use chip8::processor::operations::{self, Opcode};
+use chip8::processor::{Cpu, CpuOptions, FrameOutput};
+use std::error;
+use std::process;
+
+pub const START_ADDRESS: usize = 0x200;
+pub const QUIRKS: &str = "default";
+pub const INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+pub const PROGRAM: [u8; 14] = [
+    0x6a, 0x1c, 0x6b, 0x0d, 0xf0, 0x0a, 0x00, 0xe0, 0xf0, 0x29, 0xda, 0xb5, 0x12, 0x04,
+];
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+// run the program in a window until it is closed
+pub fn run() -> Result<(), Box<dyn error::Error>> {
+    let options = CpuOptions {
+        quirks: QUIRKS.parse()?,
+        start_address: START_ADDRESS,
+        ..CpuOptions::default()
+    };
+    let mut cpu = Cpu::with_options(&PROGRAM, &options)?;
+    let (mut display, mut input, mut timer) = host::open_sdl("keys.ch8", 10)?;
+    host::run(&mut cpu, &mut display, &mut input, &mut timer, run_frame)
+}
+
+// one 60hz frame. the profiler, coverage and trace only see instructions
+// run by the interpreter, so the whole frame is interpreted when one is on
+pub fn run_frame(cpu: &mut Cpu, keys: &[bool; 16]) -> Result<FrameOutput, Box<dyn error::Error>> {
+    if cpu.instrumented() {
+        cpu.run_frame(keys, INSTRUCTIONS_PER_FRAME)
+    } else {
+        cpu.run_frame_with(keys, INSTRUCTIONS_PER_FRAME, execute)
+    }
+}
+
+// run the instruction at the program counter, for Cpu::run_frame_with
+pub fn execute(cpu: &mut Cpu) {
+    let pc = cpu.program_counter;
+    if cpu.rewritten(pc) {
+        cpu.execute();
+        return;
+    }
+    match pc {
+        // LD VA, 0x1c
+        0x200 => {
+            cpu.cycles += 1;
+            cpu.register[0xa] = 0x1c;
+        }
+        // LD VB, 0x0d
+        0x202 => {
+            cpu.cycles += 1;
+            cpu.register[0xb] = 0x0d;
+        }
+        // LD V0, K
+        0x204 => {
+            cpu.cycles += 1;
+            cpu.keypad_waiting = true;
+            cpu.keypad_waiting_register = 0x0;
+        }
+        // CLS
+        0x206 => {
+            cpu.cycles += 1;
+            cpu.display = [0; 32];
+            cpu.redraw_flag = true;
+        }
+        // LD F, V0
+        0x208 => {
+            cpu.cycles += 1;
+            cpu.register_i = cpu.register[0x0] as u16 * 5;
+        }
+        // DRW VA, VB, 5
+        0x20a => {
+            cpu.cycles += 1;
+            operations::draw(cpu, &Opcode::from(0xdab5));
+        }
+        // JP 0x204
+        0x20c => {
+            cpu.cycles += 1;
+            cpu.program_counter = 0x202;
+        }
+        _ => cpu.execute(),
+    }
+}
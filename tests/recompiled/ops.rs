@@ -0,0 +1,282 @@
+// recompiled from ops.ch8 by chip8 recompile, regenerate it instead of editing.
+// build it as a binary of a crate that depends on chip8, src/main.rs for
+// example
+
+use chip8::host;
+use chip8::processor::operations::{self, Opcode};
+use chip8::processor::{Cpu, CpuOptions, FrameOutput};
+use std::error;
+use std::process;
+
+pub const START_ADDRESS: usize = 0x200;
+pub const QUIRKS: &str = "default";
+pub const INSTRUCTIONS_PER_FRAME: u32 = 10;
+
+pub const PROGRAM: [u8; 74] = [
+    0x6a, 0x07, 0x6b, 0x0c, 0x8a, 0xb1, 0x8a, 0xb2, 0x8a, 0xb3, 0x8a, 0xb6, 0x8b, 0xae, 0x8a, 0xb7,
+    0x8a, 0xa3, 0x8b, 0xb6, 0x5a, 0xa0, 0x6c, 0x01, 0x9b, 0xb0, 0x6d, 0x05, 0xfd, 0x15, 0xfd, 0x18,
+    0xfe, 0x07, 0xfd, 0x29, 0xfd, 0x1e, 0xc3, 0x3c, 0xa3, 0x00, 0xf3, 0x33, 0xf3, 0x65, 0xf4, 0x55,
+    0x22, 0x40, 0xe1, 0x9e, 0xe1, 0xa1, 0x72, 0x01, 0xa2, 0x3d, 0xf0, 0x55, 0x77, 0x01, 0x12, 0x00,
+    0x00, 0xe0, 0xa0, 0x00, 0xd0, 0x15, 0xfa, 0x3a, 0x00, 0xee,
+];
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+// run the program in a window until it is closed
+pub fn run() -> Result<(), Box<dyn error::Error>> {
+    let options = CpuOptions {
+        quirks: QUIRKS.parse()?,
+        start_address: START_ADDRESS,
+        ..CpuOptions::default()
+    };
+    let mut cpu = Cpu::with_options(&PROGRAM, &options)?;
+    let (mut display, mut input, mut timer) = host::open_sdl("ops.ch8", 10)?;
+    host::run(&mut cpu, &mut display, &mut input, &mut timer, run_frame)
+}
+
+// one 60hz frame. the profiler, coverage and trace only see instructions
+// run by the interpreter, so the whole frame is interpreted when one is on
+pub fn run_frame(cpu: &mut Cpu, keys: &[bool; 16]) -> Result<FrameOutput, Box<dyn error::Error>> {
+    if cpu.instrumented() {
+        cpu.run_frame(keys, INSTRUCTIONS_PER_FRAME)
+    } else {
+        cpu.run_frame_with(keys, INSTRUCTIONS_PER_FRAME, execute)
+    }
+}
+
+// run the instruction at the program counter, for Cpu::run_frame_with
+pub fn execute(cpu: &mut Cpu) {
+    let pc = cpu.program_counter;
+    if cpu.rewritten(pc) {
+        cpu.execute();
+        return;
+    }
+    match pc {
+        // LD VA, 0x07
+        0x200 => {
+            cpu.cycles += 1;
+            cpu.register[0xa] = 0x07;
+        }
+        // LD VB, 0x0c
+        0x202 => {
+            cpu.cycles += 1;
+            cpu.register[0xb] = 0x0c;
+        }
+        // OR VA, VB
+        0x204 => {
+            cpu.cycles += 1;
+            cpu.register[0xa] |= cpu.register[0xb];
+            if cpu.quirks.logic_resets_vf {
+                cpu.register[0xf] = 0;
+            }
+        }
+        // AND VA, VB
+        0x206 => {
+            cpu.cycles += 1;
+            cpu.register[0xa] &= cpu.register[0xb];
+            if cpu.quirks.logic_resets_vf {
+                cpu.register[0xf] = 0;
+            }
+        }
+        // XOR VA, VB
+        0x208 => {
+            cpu.cycles += 1;
+            cpu.register[0xa] ^= cpu.register[0xb];
+            if cpu.quirks.logic_resets_vf {
+                cpu.register[0xf] = 0;
+            }
+        }
+        // SHR VA, VB
+        0x20a => {
+            cpu.cycles += 1;
+            if cpu.quirks.shift_uses_vy {
+                cpu.register[0xa] = cpu.register[0xb];
+            }
+            let value = cpu.register[0xa];
+            cpu.register[0xa] = value >> 1;
+            cpu.register[0xf] = value & 0x1;
+        }
+        // SHL VB, VA
+        0x20c => {
+            cpu.cycles += 1;
+            if cpu.quirks.shift_uses_vy {
+                cpu.register[0xb] = cpu.register[0xa];
+            }
+            let value = cpu.register[0xb];
+            cpu.register[0xb] = value << 1;
+            cpu.register[0xf] = value >> 7;
+        }
+        // SUBN VA, VB
+        0x20e => {
+            cpu.cycles += 1;
+            let (value, borrow) = cpu.register[0xb].overflowing_sub(cpu.register[0xa]);
+            cpu.register[0xf] = !borrow as u8;
+            cpu.register[0xa] = value;
+        }
+        // XOR VA, VA
+        0x210 => {
+            cpu.cycles += 1;
+            cpu.register[0xa] = 0;
+            if cpu.quirks.logic_resets_vf {
+                cpu.register[0xf] = 0;
+            }
+        }
+        // SHR VB, VB
+        0x212 => {
+            cpu.cycles += 1;
+            let value = cpu.register[0xb];
+            cpu.register[0xb] = value >> 1;
+            cpu.register[0xf] = value & 0x1;
+        }
+        // SE VA, VA
+        0x214 => {
+            cpu.cycles += 1;
+            cpu.program_counter += 2;
+        }
+        // LD VC, 0x01
+        0x216 => {
+            cpu.cycles += 1;
+            cpu.register[0xc] = 0x01;
+        }
+        // SNE VB, VB
+        0x218 => {
+            cpu.cycles += 1;
+        }
+        // LD VD, 0x05
+        0x21a => {
+            cpu.cycles += 1;
+            cpu.register[0xd] = 0x05;
+        }
+        // LD DT, VD
+        0x21c => {
+            cpu.cycles += 1;
+            cpu.delay_timer = cpu.register[0xd];
+        }
+        // LD ST, VD
+        0x21e => {
+            cpu.cycles += 1;
+            cpu.sound_timer = cpu.register[0xd];
+        }
+        // LD VE, DT
+        0x220 => {
+            cpu.cycles += 1;
+            cpu.register[0xe] = cpu.delay_timer;
+        }
+        // LD F, VD
+        0x222 => {
+            cpu.cycles += 1;
+            cpu.register_i = cpu.register[0xd] as u16 * 5;
+        }
+        // ADD I, VD
+        0x224 => {
+            cpu.cycles += 1;
+            cpu.register_i = cpu.register_i.wrapping_add(cpu.register[0xd] as u16);
+        }
+        // RND V3, 0x3c
+        0x226 => {
+            cpu.cycles += 1;
+            operations::random_byte(cpu, &Opcode::from(0xc33c));
+        }
+        // LD I, 0x300
+        0x228 => {
+            cpu.cycles += 1;
+            cpu.register_i = 0x300;
+        }
+        // LD B, V3
+        0x22a => {
+            cpu.cycles += 1;
+            operations::store_register_x(cpu, &Opcode::from(0xf333));
+        }
+        // LD V3, [I]
+        0x22c => {
+            cpu.cycles += 1;
+            operations::read_registers(cpu, &Opcode::from(0xf365));
+        }
+        // LD [I], V4
+        0x22e => {
+            cpu.cycles += 1;
+            operations::store_registers(cpu, &Opcode::from(0xf455));
+        }
+        // CALL 0x240
+        0x230 => {
+            cpu.cycles += 1;
+            operations::call_subroutine(cpu, &Opcode::from(0x2240));
+        }
+        // SKP V1
+        0x232 => {
+            cpu.cycles += 1;
+            if cpu.keypad[cpu.register[0x1] as usize % 16] {
+                cpu.program_counter += 2;
+            }
+        }
+        // SKNP V1
+        0x234 => {
+            cpu.cycles += 1;
+            if !cpu.keypad[cpu.register[0x1] as usize % 16] {
+                cpu.program_counter += 2;
+            }
+        }
+        // ADD V2, 0x01
+        0x236 => {
+            cpu.cycles += 1;
+            let (value, carry) = cpu.register[0x2].overflowing_add(0x01);
+            cpu.register[0xf] = carry as u8;
+            cpu.register[0x2] = value;
+        }
+        // LD I, 0x23d
+        0x238 => {
+            cpu.cycles += 1;
+            cpu.register_i = 0x23d;
+        }
+        // LD [I], V0
+        0x23a => {
+            cpu.cycles += 1;
+            operations::store_registers(cpu, &Opcode::from(0xf055));
+        }
+        // ADD V7, 0x01
+        0x23c => {
+            cpu.cycles += 1;
+            let (value, carry) = cpu.register[0x7].overflowing_add(0x01);
+            cpu.register[0xf] = carry as u8;
+            cpu.register[0x7] = value;
+        }
+        // JP 0x200
+        0x23e => {
+            cpu.cycles += 1;
+            cpu.program_counter = 0x1fe;
+        }
+        // CLS
+        0x240 => {
+            cpu.cycles += 1;
+            cpu.display = [0; 32];
+            cpu.redraw_flag = true;
+        }
+        // LD I, 0x000
+        0x242 => {
+            cpu.cycles += 1;
+            cpu.register_i = 0x000;
+        }
+        // DRW V0, V1, 5
+        0x244 => {
+            cpu.cycles += 1;
+            operations::draw(cpu, &Opcode::from(0xd015));
+        }
+        // PITCH VA
+        0x246 => {
+            cpu.cycles += 1;
+            cpu.pitch = cpu.register[0xa];
+            cpu.audio_flag = true;
+        }
+        // RET
+        0x248 => {
+            cpu.cycles += 1;
+            operations::return_from_subroutine(cpu, &Opcode::from(0x00ee));
+        }
+        _ => cpu.execute(),
+    }
+}
@@ -0,0 +1,46 @@
+use chip8::testing::RomTest;
+use std::path::PathBuf;
+
+// whole programs run through the interpreter, see tests/roms/README.md for
+// what each one does. CHIP8_BLESS=1 cargo test rewrites the golden files
+
+// relative to the crate, cargo runs tests from the package directory but other
+// runners may not
+fn path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join(name)
+}
+
+#[test]
+fn test_font() {
+    let mut test = RomTest::load(path("roms/font.ch8"));
+    test.run_frames(30);
+
+    test.assert_screen(path("golden/font.txt"));
+    test.assert_register(0, 0x10);
+}
+
+#[test]
+fn test_keys() {
+    let mut test = RomTest::load(path("roms/keys.ch8"));
+    test.run_frames(5);
+    assert!(test.screen().chars().all(|c| c != '#'));
+
+    test.tap(0xa, 2).run_frames(5);
+    test.assert_screen(path("golden/keys_a.pbm"));
+    test.assert_register(0, 0xa);
+
+    test.tap(0x3, 2).run_frames(5);
+    test.assert_screen(path("golden/keys_3.pbm"));
+}
+
+#[test]
+fn test_alu() {
+    let mut test = RomTest::load(path("roms/alu.ch8"));
+    test.run_frames(2);
+
+    // 0x14 + 0x33, 0xf0 + 0x20 with carry, 5 - 8 with borrow
+    test.assert_memory(0x300, &[0x47, 0x33, 0x10, 0x20, 0x01, 0xfd, 0x08, 0x00]);
+    assert_eq!(test.cpu.program_counter, 0x21a);
+}